@@ -0,0 +1,28 @@
+// ESM loader hooks compiling TypeScript with twasm:
+//   node --loader ./node/loader.mjs app.ts
+// Build the package first with `make build-node`.
+import { readFile } from 'fs/promises';
+import { createRequire } from 'module';
+import { fileURLToPath } from 'url';
+
+const { compile, configure, options } = createRequire(import.meta.url)('../pkg-node/twasm.js');
+
+const typescript = url => /\.tsx?$/.test(url);
+
+export async function resolve(specifier, context, next) {
+    try {
+        return await next(specifier, context);
+    } catch (error) {
+        if (!specifier.startsWith('.') || typescript(specifier)) throw error;
+        return next(`${specifier}.ts`, context);
+    }
+}
+
+export async function load(url, context, next) {
+    if (!typescript(url)) return next(url, context);
+    const filename = fileURLToPath(url);
+    // Keeps whatever else the app configured, e.g. `define`.
+    if (options().module !== 'es') configure({ ...options(), module: 'es' });
+    const { code } = compile(filename, await readFile(filename, 'utf8'));
+    return { format: 'module', source: code, shortCircuit: true };
+}
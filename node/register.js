@@ -0,0 +1,14 @@
+// Registers twasm as the `require` hook for TypeScript files:
+//   node -r ./node/register.js app.ts
+// Build the package first with `make build-node`.
+const fs = require('fs');
+const { compile, configure, options } = require('../pkg-node/twasm.js');
+
+const load = (module, filename) => {
+    // Keeps whatever else the app configured, e.g. `define`.
+    if (options().module !== 'commonjs') configure({ ...options(), module: 'commonjs' });
+    module._compile(compile(filename, fs.readFileSync(filename, 'utf8')).code, filename);
+};
+
+require.extensions['.ts'] = load;
+require.extensions['.tsx'] = load;
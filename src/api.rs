@@ -0,0 +1,159 @@
+//! The JS API. Exports are camelCase and typed through the interfaces below, which wasm-pack
+//! appends to the generated `twasm.d.ts`; other modules export the rest (`configure`,
+//! `clearCache`, `startPool`, `hmrConnect`, `onError`, `vfs`, `setLoader`,
+//! `registerPlugin`, `unregisterPlugin`) against the same types.
+
+use js_sys::Promise;
+use wasm_bindgen::{JsCast, prelude::*};
+use wasm_bindgen_futures::JsFuture;
+
+use crate::{Compiled, compile, deps, dts, evaluate, load, options, prefetch, runtime};
+
+#[wasm_bindgen(typescript_custom_section)]
+const TYPES: &'static str = r#"
+export type CommentMode = "none" | "all" | "legal";
+export type ModuleFormat = "amd" | "umd" | "system" | "commonjs" | "es";
+export type Evaluator = "function" | "eval" | "blob" | "script";
+
+export interface Options {
+    minify?: boolean;
+    comments?: CommentMode;
+    module?: ModuleFormat;
+    /** Global the exports of a UMD module are assigned to without a module loader. */
+    umdName?: string;
+    /** Globals standing in for import specifiers in UMD output, e.g. `{ react: "React" }`. */
+    globals?: Record<string, string>;
+    evaluator?: Evaluator;
+    /** CSP nonce for injected scripts; defaults to the page's first `<script nonce>`. */
+    nonce?: string;
+    /** Trusted Types policy name for code and script URLs. */
+    trustedTypes?: string;
+    overlay?: boolean;
+    /**
+     * Compile-time constants, e.g. `{ "process.env.NODE_ENV": '"production"', __DEV__: false }`.
+     * Strings are JSON or dotted identifiers; other values are used as their JSON.
+     */
+    define?: Record<string, string | number | boolean | null>;
+}
+
+/** Called on each node of that `type` after its children; `null` removes it, `undefined` keeps it. */
+export type Visitor = Record<string, (node: any, filename: string) => any>;
+
+export interface Compiled {
+    code: string;
+    map: string;
+}
+
+export interface Range {
+    start: number;
+    end: number;
+}
+
+export interface Dependency {
+    specifier: string;
+    kind: "import" | "reExport" | "dynamicImport";
+    /** Imported names; `null` for a whole namespace. */
+    names: string[] | null;
+    typeOnly: boolean;
+    span: Range;
+}
+
+export interface Export {
+    name: string;
+    typeOnly: boolean;
+    span: Range;
+}
+
+export interface ModuleInfo {
+    dependencies: Dependency[];
+    exports: Export[];
+}
+
+export interface Location {
+    file: string;
+    line: number;
+    column: number;
+    snippet: string | null;
+}
+
+export interface Diagnostic {
+    message: string;
+    location: Location | null;
+}
+
+export interface ErrorReport {
+    message: string;
+    module: string | null;
+    frames: Location[];
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "Options")]
+    pub type OptionsObject;
+    #[wasm_bindgen(typescript_type = "Compiled")]
+    pub type CompiledObject;
+    #[wasm_bindgen(typescript_type = "ModuleInfo")]
+    pub type ModuleInfoObject;
+    #[wasm_bindgen(typescript_type = "(report: ErrorReport) => void")]
+    pub type ErrorCallback;
+    #[wasm_bindgen(typescript_type = "(path: string, change: \"write\" | \"delete\") => void")]
+    pub type VfsCallback;
+    #[wasm_bindgen(typescript_type = "(path: string) => string | null | Promise<string | null>")]
+    pub type LoaderCallback;
+    #[wasm_bindgen(typescript_type = "Visitor")]
+    pub type Visitor;
+}
+
+/// Compiles without evaluating. Needs no DOM, so workers can compile and post the result back to
+/// the page.
+#[wasm_bindgen(js_name = compile)]
+pub fn compile_js(filename: &str, input: &str) -> Result<CompiledObject, JsValue> {
+    Ok(compile(filename, input, &options::current()).and_then(|compiled| compiled.to_js())?.unchecked_into())
+}
+
+/// Emits the `.d.ts` for a fully annotated module.
+#[wasm_bindgen]
+pub fn declarations(filename: &str, input: &str) -> Result<String, JsValue> {
+    Ok(dts::declarations(filename, input)?)
+}
+
+/// Lists a module's imports, re-exports, literal dynamic imports and exports without compiling it.
+#[wasm_bindgen]
+pub fn dependencies(filename: &str, input: &str) -> Result<ModuleInfoObject, JsValue> {
+    let info = deps::analyze(filename, input)?;
+    let json = serde_json::to_string(&info).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(js_sys::JSON::parse(&json)?.unchecked_into())
+}
+
+/// Evaluates the output of `compile`, possibly compiled in another thread, and returns its module
+/// key.
+#[wasm_bindgen(js_name = evaluate)]
+pub fn evaluate_js(filename: &str, compiled: &CompiledObject) -> Result<f64, JsValue> {
+    let compiled = Compiled::from_js(compiled).ok_or_else(|| JsValue::from_str("expected { code, map }"))?;
+    Ok(evaluate(filename, &compiled)? as f64)
+}
+
+async fn exports(key: u64) -> Result<JsValue, JsValue> {
+    JsFuture::from(runtime::exports(key as f64)).await
+}
+
+/// Compiles and runs `source` as the module at `path`, resolving to its exports.
+#[wasm_bindgen(js_name = registerModule)]
+pub async fn register_module(path: String, source: String) -> Result<JsValue, JsValue> {
+    exports(load(&path, &source).await?).await
+}
+
+/// Fetches the module graph at `url`, then runs it, resolving to the module's exports.
+#[wasm_bindgen(js_name = importModule)]
+pub async fn import_module(url: String) -> Result<JsValue, JsValue> {
+    exports(prefetch::import(&url).await?).await
+}
+
+/// The loader `runtime.js` calls for every import, resolving to the imported module's key.
+pub(crate) fn loader() -> Closure<dyn FnMut(String) -> Promise> {
+    Closure::wrap(Box::new(|path: String| wasm_bindgen_futures::future_to_promise(async move {
+        Ok(JsValue::from_f64(prefetch::import(&path).await? as f64))
+    })) as Box<dyn FnMut(String) -> Promise>)
+}
@@ -0,0 +1,89 @@
+use std::{env, fs, path::PathBuf, process};
+use twasm::cache::{FsCache, MemoryCache, compile_cached};
+
+fn usage() -> ! {
+    eprintln!("usage: twasm bundle <entry.ts> [-o <out.js>] [--minify] [--comments none|all|legal] [--define <name>=<value>]...");
+    eprintln!("       twasm compile <file.ts> [-o <out.js>] [--minify] [--comments none|all|legal] [--module amd|umd|system|commonjs|es]");
+    eprintln!("                      [--umd-name <global>] [--global <specifier>=<global>]... [--define <name>=<value>]... [--no-cache]");
+    eprintln!("       twasm dts <file.ts> [-o <out.d.ts>]");
+    process::exit(2)
+}
+
+fn fail<E: std::fmt::Debug>(e: E) -> ! {
+    eprintln!("{:?}", e);
+    process::exit(1)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let command = args.first().cloned().unwrap_or_else(|| usage());
+
+    let mut input = None;
+    let mut out = None;
+    let mut no_cache = false;
+    let mut options = twasm::Options::default();
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "-o" | "--out" => out = Some(PathBuf::from(rest.next().unwrap_or_else(|| usage()))),
+            "--minify" => options.minify = true,
+            "--comments" => options.comments = rest.next().and_then(|m| twasm::CommentMode::from_name(m)).unwrap_or_else(|| usage()),
+            "--module" => options.module = rest.next().and_then(|m| twasm::ModuleFormat::from_name(m)).unwrap_or_else(|| usage()),
+            "--umd-name" => options.umd.name = Some(rest.next().unwrap_or_else(|| usage()).clone()),
+            "--global" => {
+                let mapping = rest.next().unwrap_or_else(|| usage());
+                let (spec, global) = mapping.split_at(mapping.find('=').unwrap_or_else(|| usage()));
+                options.umd.globals.insert(spec.to_string(), global[1..].to_string());
+            }
+            "--define" => {
+                let mapping = rest.next().unwrap_or_else(|| usage());
+                let (name, value) = mapping.split_at(mapping.find('=').unwrap_or_else(|| usage()));
+                options.define.insert(name.to_string(), value[1..].to_string());
+            }
+            "--no-cache" => no_cache = true,
+            _ if input.is_none() => input = Some(PathBuf::from(arg)),
+            _ => usage(),
+        }
+    }
+    let input = input.unwrap_or_else(|| usage());
+
+    if command == "dts" {
+        let source = fs::read_to_string(&input).unwrap_or_else(|e| fail(e));
+        let dts = twasm::dts::declarations(&input.to_string_lossy(), &source).unwrap_or_else(|e| fail(e));
+        match out {
+            None => print!("{}", dts),
+            Some(out) => fs::write(&out, dts).unwrap_or_else(|e| fail(e)),
+        }
+        return;
+    }
+
+    let (code, map) = match command.as_str() {
+        "bundle" => {
+            let bundle = twasm::bundle::bundle(&input, &options).unwrap_or_else(|e| fail(e));
+            (bundle.code, bundle.map)
+        }
+        "compile" => {
+            let source = fs::read_to_string(&input).unwrap_or_else(|e| fail(e));
+            let filename = input.to_string_lossy();
+            let compiled = if no_cache {
+                compile_cached(&MemoryCache::default(), &filename, &source, &options)
+            } else {
+                compile_cached(&FsCache::default(), &filename, &source, &options)
+            };
+            let compiled = compiled.unwrap_or_else(|e| fail(e));
+            (compiled.code, compiled.map)
+        }
+        _ => usage(),
+    };
+
+    match out {
+        None => print!("{}", code),
+        Some(out) => {
+            let map_path = out.with_extension("js.map");
+            let name = map_path.file_name().map(|f| f.to_string_lossy().to_string()).unwrap_or_default();
+            fs::write(&out, format!("{}//# sourceMappingURL={}\n", code, name))
+                .and_then(|_| fs::write(&map_path, map))
+                .unwrap_or_else(|e| fail(e));
+        }
+    }
+}
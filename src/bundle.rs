@@ -0,0 +1,222 @@
+//! Bundler mode. Walks the import graph from an entrypoint with the same resolution rules as the
+//! runtime loader, drops exports nothing imports, and concatenates the AMD output of every module
+//! behind a small synchronous `define`/`require` prelude, producing one script and its source map.
+
+use std::{collections::VecDeque, fs, path::Path};
+use fxhash::{FxHashMap, FxHashSet};
+use swc_common::{DUMMY_SP, LineCol, SourceMap, comments::SingleThreadedComments, errors::{ColorConfig, Handler}, sync::Lrc};
+use swc_ecma_ast::{Decl, ExportSpecifier, Expr, Ident, Invalid, Module, ModuleDecl, ModuleItem, Pat, Stmt, Str};
+use swc_ecma_utils::DestructuringFinder;
+use swc_ecma_visit::{Fold, FoldWith, Node, Visit, VisitWith, noop_fold_type, noop_visit_type};
+
+use crate::{Error, Options, Result, define, deps, emit, isolated, minify, parse, plugin, resolve::resolve};
+
+const PRELUDE: &str = "(function() {
+var modules = {}, cache = {};
+function define(id, deps, factory) { modules[id] = [deps, factory]; }
+function require(id) {
+    if (!cache[id]) {
+        var exports = cache[id] = {};
+        modules[id][1].apply(null, modules[id][0].map(function(dep) { return dep === \"exports\" ? exports : require(dep); }));
+    }
+    return cache[id];
+}
+";
+
+#[derive(Debug)]
+pub struct Bundle {
+    pub code: String,
+    pub map: String,
+}
+
+enum Used {
+    All,
+    Names(FxHashSet<String>),
+}
+impl Used {
+    fn contains(&self, name: &str) -> bool {
+        match self { Used::All => true, Used::Names(names) => names.contains(name) }
+    }
+}
+
+/// Rewrites import sources to the resolved module ids and records which names each import uses,
+/// `None` standing for the whole namespace.
+struct Link<'a> {
+    from: &'a str,
+    deps: Vec<(String, Option<Vec<String>>)>,
+}
+impl Link<'_> {
+    /// Ids are relative to the entrypoint's directory, which an absolute import is resolved
+    /// against too, as with `loader::Fs`.
+    fn link(&mut self, src: Str, names: Option<Vec<String>>) -> Str {
+        let id = resolve(self.from, &src.value).trim_start_matches('/').to_string();
+        self.deps.push((id.clone(), names));
+        Str { value: id.into(), ..src }
+    }
+}
+impl Fold for Link<'_> {
+    noop_fold_type!();
+
+    fn fold_module_decl(&mut self, decl: ModuleDecl) -> ModuleDecl {
+        match decl {
+            ModuleDecl::Import(mut import) => {
+                let names = deps::import_names(&import);
+                import.src = self.link(import.src, names);
+                ModuleDecl::Import(import)
+            }
+            ModuleDecl::ExportNamed(mut export) => {
+                if let Some(src) = export.src.take() {
+                    let names = deps::reexport_names(&export);
+                    export.src = Some(self.link(src, names));
+                }
+                ModuleDecl::ExportNamed(export)
+            }
+            ModuleDecl::ExportAll(mut export) => {
+                export.src = self.link(export.src, None);
+                ModuleDecl::ExportAll(export)
+            }
+            decl => decl,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Refs(FxHashMap<String, usize>);
+impl Visit for Refs {
+    noop_visit_type!();
+
+    fn visit_ident(&mut self, ident: &Ident, _: &dyn Node) {
+        *self.0.entry(ident.sym.to_string()).or_default() += 1;
+    }
+}
+
+fn declared(decl: &Decl) -> Vec<Ident> {
+    match decl {
+        Decl::Fn(f) => vec![f.ident.clone()],
+        Decl::Class(c) => vec![c.ident.clone()],
+        Decl::Var(var) => {
+            let mut found = vec![];
+            var.decls.visit_with(&Invalid { span: DUMMY_SP } as _, &mut DestructuringFinder { found: &mut found });
+            found
+        }
+        _ => vec![],
+    }
+}
+
+fn pure(expr: &Expr) -> bool {
+    matches!(expr, Expr::Arrow(_) | Expr::Fn(_) | Expr::Lit(_) | Expr::Class(_))
+}
+
+/// Removes top-level declarations that are only referenced by their own binding, repeating until
+/// nothing changes so chains of helpers used only by dropped exports go too.
+fn prune(mut module: Module) -> Module {
+    loop {
+        let mut refs = Refs::default();
+        module.visit_with(&Invalid { span: DUMMY_SP } as _, &mut refs);
+        let unused = |ident: &Ident| refs.0.get(&*ident.sym).is_none_or(|&n| n <= 1);
+
+        let len = module.body.len();
+        module.body.retain(|item| match item {
+            ModuleItem::Stmt(Stmt::Decl(Decl::Fn(f))) => !unused(&f.ident),
+            ModuleItem::Stmt(Stmt::Decl(Decl::Class(c))) => !unused(&c.ident),
+            ModuleItem::Stmt(Stmt::Decl(Decl::Var(var))) => !var.decls.iter().all(|d| match &d.name {
+                Pat::Ident(binding) => unused(&binding.id) && d.init.as_deref().is_none_or(pure),
+                _ => false,
+            }),
+            _ => true,
+        });
+        if module.body.len() == len {
+            return module;
+        }
+    }
+}
+
+fn shake(module: Module, used: &Used) -> Module {
+    if let Used::All = used {
+        return module;
+    }
+
+    let body = module.body.into_iter().filter_map(|item| match item {
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(export)) if !declared(&export.decl).iter().any(|i| used.contains(&i.sym)) =>
+            Some(ModuleItem::Stmt(Stmt::Decl(export.decl))),
+        ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(mut export)) if export.src.is_none() => {
+            export.specifiers.retain(|s| match s {
+                ExportSpecifier::Named(s) => used.contains(&s.exported.as_ref().unwrap_or(&s.orig).sym),
+                _ => true,
+            });
+            if export.specifiers.is_empty() { None } else { Some(ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(export))) }
+        }
+        item => Some(item),
+    }).collect();
+
+    prune(Module { body, ..module })
+}
+
+/// Bundles `entry` and everything it imports into a single script. Module ids are paths relative
+/// to the entrypoint's directory.
+pub fn bundle(entry: &Path, options: &Options) -> Result<Bundle> {
+    let root = entry.parent().unwrap_or_else(|| Path::new(""));
+    let entry = entry.file_name().map(|f| f.to_string_lossy().to_string()).unwrap_or_default();
+
+    swc_common::GLOBALS.set(&swc_common::Globals::new(), || {
+        let cm: Lrc<SourceMap> = Default::default();
+        let handler = Handler::with_tty_emitter(ColorConfig::Auto, true, false, Some(cm.clone()));
+        let comments = SingleThreadedComments::default();
+
+        let mut modules = vec![];
+        let mut used = FxHashMap::default();
+        used.insert(entry.clone(), Used::All);
+        let mut queue = VecDeque::from(vec![entry.clone()]);
+        while let Some(id) = queue.pop_front() {
+            let input = fs::read_to_string(root.join(&id))?;
+            let mut link = Link { from: &id, deps: vec![] };
+            let module = parse(&cm, &handler, &comments, &id, &input)?;
+            if !isolated::check(&module, &handler) {
+                return Err(Error::DiagnosticEmitted);
+            }
+            let module = module.fold_with(&mut swc_ecma_transforms_typescript::strip());
+            let module = define::define(module, &options.define)?;
+            let module = plugin::apply(module, &id)?.fold_with(&mut link);
+
+            for (dep, names) in link.deps {
+                if !used.contains_key(&dep) {
+                    queue.push_back(dep.clone());
+                }
+                let entry = used.entry(dep).or_insert_with(|| Used::Names(Default::default()));
+                match (entry, names) {
+                    (Used::All, _) => {}
+                    (entry, None) => *entry = Used::All,
+                    (Used::Names(set), Some(names)) => set.extend(names),
+                }
+            }
+            modules.push((id, module));
+        }
+
+        let mut code = PRELUDE.to_string();
+        let mut srcmap = vec![];
+        for (id, module) in modules {
+            let mut module = shake(module, &used[&id])
+                .fold_with(&mut swc_ecma_transforms_module::amd::amd(Default::default()));
+            if options.minify {
+                module = minify(module, &comments);
+            }
+
+            let line = code.matches('\n').count() as u32;
+            let head = format!("define({:?}, ", id);
+            let mut map = vec![];
+            let output = emit(cm.clone(), &comments, &[], &module, Some(&mut map), options)?;
+            srcmap.extend(map.into_iter().map(|(pos, lc)| (pos, LineCol {
+                line: lc.line + line,
+                col: if lc.line == 0 { lc.col + head.len() as u32 - 7 } else { lc.col },
+            })));
+            code.push_str(&output.replacen("define(", &head, 1));
+            code.push('\n');
+        }
+        code.push_str(&format!("require({:?});\n}})();\n", entry));
+
+        let mut map = vec![];
+        cm.build_source_map(&mut srcmap).to_writer(&mut map)?;
+
+        Ok(Bundle { code, map: String::from_utf8_lossy(&map).to_string() })
+    })
+}
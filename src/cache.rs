@@ -0,0 +1,148 @@
+//! Compile caches. Entries are keyed by a SHA-256 digest of the filename, source text, the compile
+//! options that shape the output, plugin names and compiler version, so an edited file or an
+//! upgraded twasm never sees a stale result. The browser
+//! persists them in Cache Storage; native callers plug in any `CompileCache`.
+
+use std::{fs, path::PathBuf, sync::{RwLock, atomic::{AtomicUsize, Ordering}}};
+use fxhash::FxHashMap;
+use js_sys::JSON;
+use wasm_bindgen::{JsCast, prelude::*};
+use wasm_bindgen_futures::JsFuture;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use web_sys::{Cache, Response};
+
+use crate::{Compiled, Options, Result, compile, global, plugin};
+
+const NAME: &str = "twasm";
+
+/// The hex digest of everything that shapes the output, serialized as JSON with sorted maps.
+pub fn key(filename: &str, input: &str, options: &Options) -> String {
+    // Evaluation settings such as the per-response CSP nonce leave the output alone and stay out.
+    let Options { minify, comments, module, umd, define, .. } = options;
+    let shape = json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "filename": filename,
+        "input": input,
+        "plugins": plugin::names(),
+        "minify": minify,
+        "comments": comments.name(),
+        "module": module.name(),
+        "umd": { "name": umd.name, "globals": umd.globals },
+        "define": define,
+    });
+    Sha256::digest(shape.to_string().as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub trait CompileCache {
+    fn get(&self, key: &str) -> Option<Compiled>;
+    fn put(&self, key: &str, compiled: &Compiled) -> Result<()>;
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryCache(RwLock<FxHashMap<String, Compiled>>);
+
+impl CompileCache for MemoryCache {
+    fn get(&self, key: &str) -> Option<Compiled> {
+        self.0.read().ok()?.get(key).cloned()
+    }
+
+    fn put(&self, key: &str, compiled: &Compiled) -> Result<()> {
+        self.0.write()?.insert(key.to_string(), compiled.clone());
+        Ok(())
+    }
+}
+
+/// Stores each entry as `<key>.js` and `<key>.js.map` in a directory, by default
+/// `$XDG_CACHE_HOME/twasm` or `~/.cache/twasm`.
+#[derive(Debug, Clone)]
+pub struct FsCache {
+    dir: PathBuf,
+}
+
+impl FsCache {
+    pub fn new(dir: impl Into<PathBuf>) -> FsCache {
+        FsCache { dir: dir.into() }
+    }
+
+    /// Writes `name` through a temporary file beside it, so a crash or another process never leaves
+    /// it partly written.
+    fn write(&self, name: &str, contents: &str) -> Result<()> {
+        static TEMP: AtomicUsize = AtomicUsize::new(0);
+        let temp = self.dir.join(format!(".{}.{}-{}.tmp", name, std::process::id(), TEMP.fetch_add(1, Ordering::Relaxed)));
+        fs::write(&temp, contents)?;
+        fs::rename(&temp, self.dir.join(name)).inspect_err(|_| { fs::remove_file(&temp).ok(); })?;
+        Ok(())
+    }
+}
+
+impl Default for FsCache {
+    fn default() -> FsCache {
+        let base = std::env::var_os("XDG_CACHE_HOME").map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .unwrap_or_else(std::env::temp_dir);
+        FsCache::new(base.join(NAME))
+    }
+}
+
+impl CompileCache for FsCache {
+    fn get(&self, key: &str) -> Option<Compiled> {
+        let code = fs::read_to_string(self.dir.join(format!("{}.js", key))).ok()?;
+        let map = fs::read_to_string(self.dir.join(format!("{}.js.map", key))).ok()?;
+        Some(Compiled { code, map })
+    }
+
+    fn put(&self, key: &str, compiled: &Compiled) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // `get` reads the code first, so the map is in place whenever the code is.
+        self.write(&format!("{}.js.map", key), &compiled.map)?;
+        self.write(&format!("{}.js", key), &compiled.code)
+    }
+}
+
+/// Compiles through `cache`, only running swc on a miss.
+pub fn compile_cached(cache: &dyn CompileCache, filename: &str, input: &str, options: &Options) -> Result<Compiled> {
+    let key = key(filename, input, options);
+    if let Some(compiled) = cache.get(&key) {
+        return Ok(compiled);
+    }
+    let compiled = compile(filename, input, options)?;
+    cache.put(&key, &compiled)?;
+    Ok(compiled)
+}
+
+fn url(key: &str) -> String {
+    format!("/__twasm_cache/{}", key)
+}
+
+async fn open() -> Result<Cache> {
+    let caches = global::scope()?.caches()?;
+    Ok(JsFuture::from(caches.open(NAME)).await?.dyn_into()?)
+}
+
+pub(crate) async fn get(key: &str) -> Result<Option<Compiled>> {
+    let found = JsFuture::from(open().await?.match_with_str(&url(key))).await?;
+    if found.is_undefined() {
+        return Ok(None);
+    }
+    let text = JsFuture::from(found.dyn_into::<Response>()?.text()?).await?;
+    Ok(Compiled::from_js(&JSON::parse(&text.as_string().unwrap_or_default())?))
+}
+
+pub(crate) async fn put(key: &str, compiled: &Compiled) -> Result<()> {
+    let body = String::from(JSON::stringify(&compiled.to_js()?)?);
+    JsFuture::from(open().await?.put_with_str(&url(key), &Response::new_with_opt_str(Some(&body))?)).await?;
+    Ok(())
+}
+
+pub(crate) async fn delete(key: &str) -> Result<()> {
+    JsFuture::from(open().await?.delete_with_str(&url(key))).await?;
+    Ok(())
+}
+
+#[wasm_bindgen(js_name = clearCache)]
+pub async fn clear_cache() -> std::result::Result<(), JsValue> {
+    let caches = global::scope().and_then(|scope| scope.caches()).map_err(|e| JsValue::from_str(&format!("{:?}", e)))?;
+    JsFuture::from(caches.delete(NAME)).await?;
+    Ok(())
+}
@@ -0,0 +1,173 @@
+//! Compile-time constants, like esbuild's `define`. Each key is a global name or dotted path such
+//! as `__DEV__`, `process.env.NODE_ENV` or `import.meta.env.MODE`, and each value is JSON or
+//! another dotted identifier, e.g. `"\"production\""` or `false`. Matches are replaced where they
+//! are read and resolve to a global, so locals of the same name and assignment targets are left
+//! alone; swc's simplifier then folds the now-constant conditions and drops the dead branches, so
+//! one source compiles differently for dev and prod pages.
+
+use std::collections::BTreeMap;
+use fxhash::{FxHashMap, FxHashSet};
+use swc_common::{DUMMY_SP, Mark};
+use swc_ecma_ast::{
+    AssignExpr, BindingIdent, Bool, ClassDecl, ClassExpr, Expr, ExprOrSuper, FnDecl, FnExpr, ImportDefaultSpecifier,
+    ImportNamedSpecifier, ImportStarAsSpecifier, Invalid, Lit, MemberExpr, MetaPropExpr, Module, Null, Number, Pat, PatOrExpr,
+    UpdateExpr,
+};
+use swc_ecma_transforms_base::{fixer::fixer, hygiene::hygiene, resolver::resolver_with_mark};
+use swc_ecma_transforms_optimization::simplify::simplifier;
+use swc_ecma_utils::{Id, ident::IdentLike, quote_ident, quote_str};
+use swc_ecma_visit::{Fold, FoldWith, Node, Visit, VisitWith, noop_fold_type, noop_visit_type};
+
+use crate::{Error, Result};
+
+/// The dotted path an expression reads, e.g. `process.env["NODE_ENV"]` as `process.env.NODE_ENV`.
+fn path(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Ident(ident) => Some(ident.sym.to_string()),
+        Expr::MetaProp(MetaPropExpr { meta, prop }) => Some(format!("{}.{}", meta.sym, prop.sym)),
+        Expr::Member(MemberExpr { obj: ExprOrSuper::Expr(obj), prop, computed, .. }) => {
+            let prop = match (&**prop, computed) {
+                (Expr::Ident(prop), false) => prop.sym.to_string(),
+                (Expr::Lit(Lit::Str(prop)), true) => prop.value.to_string(),
+                _ => return None,
+            };
+            Some(format!("{}.{}", path(obj)?, prop))
+        }
+        _ => None,
+    }
+}
+
+/// Every name the module declares, as resolved. The resolver gives an unresolved reference the
+/// top-level mark too, so a global is a reference that matches none of these.
+#[derive(Default)]
+struct Bindings(FxHashSet<Id>);
+impl Visit for Bindings {
+    noop_visit_type!();
+
+    fn visit_binding_ident(&mut self, ident: &BindingIdent, _: &dyn Node) { self.0.insert(ident.id.to_id()); }
+    fn visit_fn_decl(&mut self, decl: &FnDecl, _: &dyn Node) {
+        self.0.insert(decl.ident.to_id());
+        decl.function.visit_with(decl, self);
+    }
+    fn visit_fn_expr(&mut self, expr: &FnExpr, _: &dyn Node) {
+        self.0.extend(expr.ident.as_ref().map(|ident| ident.to_id()));
+        expr.function.visit_with(expr, self);
+    }
+    fn visit_class_decl(&mut self, decl: &ClassDecl, _: &dyn Node) {
+        self.0.insert(decl.ident.to_id());
+        decl.class.visit_with(decl, self);
+    }
+    fn visit_class_expr(&mut self, expr: &ClassExpr, _: &dyn Node) {
+        self.0.extend(expr.ident.as_ref().map(|ident| ident.to_id()));
+        expr.class.visit_with(expr, self);
+    }
+    fn visit_import_default_specifier(&mut self, s: &ImportDefaultSpecifier, _: &dyn Node) { self.0.insert(s.local.to_id()); }
+    fn visit_import_named_specifier(&mut self, s: &ImportNamedSpecifier, _: &dyn Node) { self.0.insert(s.local.to_id()); }
+    fn visit_import_star_as_specifier(&mut self, s: &ImportStarAsSpecifier, _: &dyn Node) { self.0.insert(s.local.to_id()); }
+}
+
+fn value(text: &str) -> Option<Expr> {
+    let lit = match serde_json::from_str(text) {
+        Ok(serde_json::Value::String(s)) => Lit::Str(quote_str!(s)),
+        Ok(serde_json::Value::Number(n)) => Lit::Num(Number { span: DUMMY_SP, value: n.as_f64()? }),
+        Ok(serde_json::Value::Bool(value)) => Lit::Bool(Bool { span: DUMMY_SP, value }),
+        Ok(serde_json::Value::Null) => Lit::Null(Null { span: DUMMY_SP }),
+        Ok(_) => return None,
+        Err(_) => {
+            let mut parts = text.split('.');
+            let valid = |part: &str| part.chars().next().is_some_and(|c| !c.is_ascii_digit())
+                && part.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+            let first = parts.next().filter(|part| valid(part))?;
+            return parts.try_fold(Expr::Ident(quote_ident!(first)), |obj, part| Some(Expr::Member(MemberExpr {
+                span: DUMMY_SP,
+                obj: ExprOrSuper::Expr(Box::new(obj)),
+                prop: Box::new(Expr::Ident(quote_ident!(valid(part).then_some(part)?))),
+                computed: false,
+            })));
+        }
+    };
+    Some(Expr::Lit(lit))
+}
+
+struct Define {
+    values: FxHashMap<String, Expr>,
+    bindings: FxHashSet<Id>,
+}
+
+impl Define {
+    /// Whether `expr` is rooted in a name the module doesn't declare.
+    fn global(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Ident(ident) => !self.bindings.contains(&ident.to_id()),
+            Expr::Member(MemberExpr { obj: ExprOrSuper::Expr(obj), .. }) => self.global(obj),
+            _ => true,
+        }
+    }
+
+    fn get(&self, expr: &Expr) -> Option<&Expr> {
+        path(expr).filter(|_| self.global(expr)).and_then(|path| self.values.get(&path))
+    }
+
+    fn defined_target(&self, target: &PatOrExpr) -> bool {
+        match target {
+            PatOrExpr::Expr(expr) => self.get(expr).is_some(),
+            PatOrExpr::Pat(pat) => match &**pat {
+                Pat::Expr(expr) => self.get(expr).is_some(),
+                Pat::Ident(binding) => self.get(&Expr::Ident(binding.id.clone())).is_some(),
+                _ => false,
+            },
+        }
+    }
+}
+
+impl Fold for Define {
+    noop_fold_type!();
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        match self.get(&expr) {
+            Some(value) => value.clone(),
+            None => expr.fold_children_with(self),
+        }
+    }
+
+    /// `__DEV__ = true` writes the global rather than reading it.
+    fn fold_assign_expr(&mut self, expr: AssignExpr) -> AssignExpr {
+        match self.defined_target(&expr.left) {
+            true => AssignExpr { right: expr.right.fold_with(self), ..expr },
+            false => expr.fold_children_with(self),
+        }
+    }
+
+    fn fold_update_expr(&mut self, expr: UpdateExpr) -> UpdateExpr {
+        match self.get(&expr.arg) {
+            Some(_) => expr,
+            None => expr.fold_children_with(self),
+        }
+    }
+
+    /// `a.__DEV__` names a property, not the global.
+    fn fold_member_expr(&mut self, expr: MemberExpr) -> MemberExpr {
+        match expr.computed {
+            true => expr.fold_children_with(self),
+            false => MemberExpr { obj: expr.obj.fold_with(self), ..expr },
+        }
+    }
+}
+
+/// Substitutes `define` and simplifies; a no-op without definitions.
+pub(crate) fn define(module: Module, define: &BTreeMap<String, String>) -> Result<Module> {
+    if define.is_empty() {
+        return Ok(module);
+    }
+    let values = define.iter()
+        .map(|(key, text)| Ok((key.clone(), value(text).ok_or_else(|| Error::InvalidDefine(key.clone()))?)))
+        .collect::<Result<FxHashMap<_, _>>>()?;
+    let module = module.fold_with(&mut resolver_with_mark(Mark::fresh(Mark::root())));
+    let mut bindings = Bindings::default();
+    module.visit_with(&Invalid { span: DUMMY_SP } as _, &mut bindings);
+    Ok(module
+        .fold_with(&mut Define { values, bindings: bindings.0 })
+        .fold_with(&mut simplifier(Default::default()))
+        .fold_with(&mut hygiene())
+        .fold_with(&mut fixer(None)))
+}
@@ -0,0 +1,175 @@
+//! Static analysis of a module's imports and exports, for prefetching dependencies and drawing
+//! dependency graphs without compiling. Spans are byte offsets into the source text.
+
+use serde::Serialize;
+use swc_common::{DUMMY_SP, SourceMap, Span, comments::SingleThreadedComments, errors::{ColorConfig, Handler}, sync::Lrc};
+use swc_ecma_ast::{CallExpr, Decl, ExportSpecifier, Expr, ExprOrSuper, Ident, ImportDecl, ImportSpecifier, Invalid, Lit, ModuleDecl, ModuleItem, NamedExport, TsModuleName};
+use swc_ecma_utils::DestructuringFinder;
+use swc_ecma_visit::{FoldWith, Node, Visit, VisitWith, noop_visit_type};
+
+use crate::{Result, parse};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Range {
+    pub start: u32,
+    pub end: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DependencyKind {
+    Import,
+    ReExport,
+    DynamicImport,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Dependency {
+    pub specifier: String,
+    pub kind: DependencyKind,
+    /// Imported or re-exported names; `None` when the whole namespace is used.
+    pub names: Option<Vec<String>>,
+    /// Erased by compiling: `import type`, or an import whose bindings are only used as types.
+    pub type_only: bool,
+    pub span: Range,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Export {
+    pub name: String,
+    pub type_only: bool,
+    pub span: Range,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ModuleInfo {
+    pub dependencies: Vec<Dependency>,
+    pub exports: Vec<Export>,
+}
+
+/// Names an import pulls from its source, `None` for a namespace import.
+pub(crate) fn import_names(import: &ImportDecl) -> Option<Vec<String>> {
+    import.specifiers.iter().map(|s| match s {
+        ImportSpecifier::Named(s) => Some(s.imported.as_ref().unwrap_or(&s.local).sym.to_string()),
+        ImportSpecifier::Default(_) => Some("default".to_string()),
+        ImportSpecifier::Namespace(_) => None,
+    }).collect()
+}
+
+/// Names an `export { .. } from` pulls from its source, `None` for `export * as ns`.
+pub(crate) fn reexport_names(export: &NamedExport) -> Option<Vec<String>> {
+    export.specifiers.iter().map(|s| match s {
+        ExportSpecifier::Named(s) => Some(s.orig.sym.to_string()),
+        ExportSpecifier::Default(_) => Some("default".to_string()),
+        ExportSpecifier::Namespace(_) => None,
+    }).collect()
+}
+
+struct Collector<'a> {
+    cm: &'a SourceMap,
+    info: ModuleInfo,
+}
+
+impl Collector<'_> {
+    fn range(&self, span: Span) -> Range {
+        Range { start: self.cm.lookup_byte_offset(span.lo).pos.0, end: self.cm.lookup_byte_offset(span.hi).pos.0 }
+    }
+
+    fn dependency(&mut self, specifier: &str, kind: DependencyKind, names: Option<Vec<String>>, type_only: bool, span: Span) {
+        let span = self.range(span);
+        self.info.dependencies.push(Dependency { specifier: specifier.to_string(), kind, names, type_only, span });
+    }
+
+    fn export(&mut self, name: &str, type_only: bool, span: Span) {
+        let span = self.range(span);
+        self.info.exports.push(Export { name: name.to_string(), type_only, span });
+    }
+}
+
+impl Visit for Collector<'_> {
+    noop_visit_type!();
+
+    fn visit_module_decl(&mut self, decl: &ModuleDecl, _: &dyn Node) {
+        match decl {
+            ModuleDecl::Import(import) =>
+                self.dependency(&import.src.value, DependencyKind::Import, import_names(import), import.type_only, import.span),
+            ModuleDecl::ExportNamed(export) => {
+                if let Some(src) = &export.src {
+                    self.dependency(&src.value, DependencyKind::ReExport, reexport_names(export), export.type_only, export.span);
+                }
+                for s in &export.specifiers {
+                    match s {
+                        ExportSpecifier::Named(s) => self.export(&s.exported.as_ref().unwrap_or(&s.orig).sym, export.type_only, s.span),
+                        ExportSpecifier::Namespace(s) => self.export(&s.name.sym, export.type_only, s.span),
+                        ExportSpecifier::Default(s) => self.export(&s.exported.sym, export.type_only, s.exported.span),
+                    }
+                }
+            }
+            ModuleDecl::ExportAll(export) => self.dependency(&export.src.value, DependencyKind::ReExport, None, false, export.span),
+            ModuleDecl::ExportDefaultDecl(export) => self.export("default", false, export.span),
+            ModuleDecl::ExportDefaultExpr(export) => self.export("default", false, export.span),
+            ModuleDecl::ExportDecl(export) => match &export.decl {
+                Decl::Fn(f) => self.export(&f.ident.sym, false, f.ident.span),
+                Decl::Class(c) => self.export(&c.ident.sym, false, c.ident.span),
+                Decl::Var(var) => {
+                    let mut found: Vec<Ident> = vec![];
+                    var.decls.visit_with(&Invalid { span: DUMMY_SP } as _, &mut DestructuringFinder { found: &mut found });
+                    for ident in found {
+                        self.export(&ident.sym, false, ident.span);
+                    }
+                }
+                Decl::TsEnum(e) => self.export(&e.id.sym, false, e.id.span),
+                Decl::TsModule(m) => if let TsModuleName::Ident(id) = &m.id { self.export(&id.sym, false, id.span) },
+                Decl::TsInterface(i) => self.export(&i.id.sym, true, i.id.span),
+                Decl::TsTypeAlias(t) => self.export(&t.id.sym, true, t.id.span),
+            },
+            _ => {}
+        }
+        decl.visit_children_with(self);
+    }
+
+    fn visit_call_expr(&mut self, call: &CallExpr, _: &dyn Node) {
+        if let ExprOrSuper::Expr(callee) = &call.callee {
+            if let Expr::Ident(ident) = &**callee {
+                if &*ident.sym == "import" {
+                    let specifier = match call.args.first().map(|arg| &*arg.expr) {
+                        Some(Expr::Lit(Lit::Str(s))) => Some(s.value.to_string()),
+                        Some(Expr::Tpl(tpl)) if tpl.exprs.is_empty() => tpl.quasis.first().map(|q| q.raw.value.to_string()),
+                        _ => None,
+                    };
+                    if let Some(specifier) = specifier {
+                        self.dependency(&specifier, DependencyKind::DynamicImport, None, false, call.span);
+                    }
+                }
+            }
+        }
+        call.visit_children_with(self);
+    }
+}
+
+/// Parses `input` and lists its static imports, re-exports, literal dynamic imports and exports.
+pub fn analyze(filename: &str, input: &str) -> Result<ModuleInfo> {
+    swc_common::GLOBALS.set(&swc_common::Globals::new(), || {
+        let cm: Lrc<SourceMap> = Default::default();
+        let handler = Handler::with_tty_emitter(ColorConfig::Auto, true, false, Some(cm.clone()));
+        let comments = SingleThreadedComments::default();
+
+        let module = parse(&cm, &handler, &comments, filename, input)?;
+        let mut collector = Collector { cm: &cm, info: ModuleInfo::default() };
+        module.visit_with(&Invalid { span: DUMMY_SP } as _, &mut collector);
+
+        // Strip decides which imports only bring in types, the same way `compile` does.
+        let kept: Vec<Range> = module.fold_with(&mut swc_ecma_transforms_typescript::strip()).body.iter().filter_map(|item| match item {
+            ModuleItem::ModuleDecl(ModuleDecl::Import(import)) => Some(collector.range(import.span)),
+            _ => None,
+        }).collect();
+        for dep in &mut collector.info.dependencies {
+            if dep.kind == DependencyKind::Import && !kept.contains(&dep.span) {
+                dep.type_only = true;
+            }
+        }
+        Ok(collector.info)
+    })
+}
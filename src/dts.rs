@@ -0,0 +1,411 @@
+//! Declaration emit. Turns a TypeScript module into its `.d.ts` by dropping implementations and
+//! keeping exported signatures. Only files that follow the `isolatedDeclarations` rules can be
+//! handled without a type checker: an export whose type would have to be inferred gets a
+//! diagnostic instead of a guess.
+
+use fxhash::FxHashSet;
+use swc_common::{DUMMY_SP, Span, Spanned, SourceMap, comments::SingleThreadedComments, errors::{ColorConfig, Handler}, sync::Lrc};
+use swc_ecma_ast::{
+    Accessibility, AssignPat, BindingIdent, Class, ClassDecl, ClassExpr, ClassMember, ClassMethod, ClassProp, Constructor, Decl,
+    DefaultDecl, ExportDecl, ExportDefaultDecl, ExportDefaultExpr, ExportNamedSpecifier, Expr, FnDecl, FnExpr, Function, Ident,
+    Invalid, Lit, MethodKind, Module, ModuleDecl, ModuleItem, Param, ParamOrTsParamProp, Pat, PrivateName, PrivateProp, PropName,
+    Stmt, TsEntityName, TsKeywordType, TsKeywordTypeKind, TsLit, TsLitType, TsParamProp, TsParamPropParam, TsType, TsTypeAnn,
+    VarDecl, VarDeclKind, VarDeclarator,
+};
+use swc_ecma_utils::{DestructuringFinder, quote_ident};
+use swc_ecma_visit::{Node, Visit, VisitWith};
+
+use crate::{CommentMode, Error, Options, Result, emit, parse};
+
+fn annotated(pat: &Pat) -> bool {
+    match pat {
+        Pat::Ident(binding) => binding.type_ann.is_some(),
+        Pat::Array(array) => array.type_ann.is_some(),
+        Pat::Object(object) => object.type_ann.is_some(),
+        Pat::Rest(rest) => rest.type_ann.is_some(),
+        _ => false,
+    }
+}
+
+/// The type of a literal initializer: the literal itself for `const`, widened to its primitive
+/// otherwise.
+fn literal_type(expr: &Expr, widen: bool) -> Option<TsTypeAnn> {
+    let span = expr.span();
+    let keyword = |kind| TsType::TsKeywordType(TsKeywordType { span, kind });
+    let ty = match expr {
+        Expr::Lit(Lit::Str(s)) if !widen => TsType::TsLitType(TsLitType { span, lit: TsLit::Str(s.clone()) }),
+        Expr::Lit(Lit::Num(n)) if !widen => TsType::TsLitType(TsLitType { span, lit: TsLit::Number(*n) }),
+        Expr::Lit(Lit::Bool(b)) if !widen => TsType::TsLitType(TsLitType { span, lit: TsLit::Bool(*b) }),
+        Expr::Lit(Lit::Str(_)) => keyword(TsKeywordTypeKind::TsStringKeyword),
+        Expr::Tpl(tpl) if tpl.exprs.is_empty() => keyword(TsKeywordTypeKind::TsStringKeyword),
+        Expr::Lit(Lit::Num(_)) => keyword(TsKeywordTypeKind::TsNumberKeyword),
+        Expr::Lit(Lit::Bool(_)) => keyword(TsKeywordTypeKind::TsBooleanKeyword),
+        _ => return None,
+    };
+    Some(TsTypeAnn { span, type_ann: Box::new(ty) })
+}
+
+/// A method's name for matching overloads; `None` for computed names.
+fn key(name: &PropName) -> Option<String> {
+    match name {
+        PropName::Ident(ident) => Some(ident.sym.to_string()),
+        PropName::Str(s) => Some(s.value.to_string()),
+        PropName::Num(n) => Some(n.value.to_string()),
+        _ => None,
+    }
+}
+
+/// The names a declaration binds.
+fn names(decl: &Decl) -> Vec<String> {
+    match decl {
+        Decl::Fn(f) => vec![f.ident.sym.to_string()],
+        Decl::Class(c) => vec![c.ident.sym.to_string()],
+        Decl::Var(var) => {
+            let mut found: Vec<Ident> = vec![];
+            var.decls.visit_with(&Invalid { span: DUMMY_SP } as _, &mut DestructuringFinder { found: &mut found });
+            found.into_iter().map(|ident| ident.sym.to_string()).collect()
+        }
+        _ => vec![],
+    }
+}
+
+/// Names the emitted declarations refer to: types, `typeof` queries, base classes and the locals
+/// an `export { .. }` or `export default` points at.
+#[derive(Default)]
+struct Refs(FxHashSet<String>);
+impl Visit for Refs {
+    fn visit_ts_entity_name(&mut self, name: &TsEntityName, _: &dyn Node) {
+        match name {
+            TsEntityName::Ident(ident) => { self.0.insert(ident.sym.to_string()); }
+            TsEntityName::TsQualifiedName(name) => name.left.visit_with(&**name as _, self),
+        }
+    }
+
+    fn visit_class(&mut self, class: &Class, _: &dyn Node) {
+        if let Some(Expr::Ident(ident)) = class.super_class.as_deref() {
+            self.0.insert(ident.sym.to_string());
+        }
+        class.visit_children_with(self);
+    }
+
+    fn visit_export_default_expr(&mut self, export: &ExportDefaultExpr, _: &dyn Node) {
+        if let Expr::Ident(ident) = &*export.expr {
+            self.0.insert(ident.sym.to_string());
+        }
+    }
+
+    fn visit_export_named_specifier(&mut self, s: &ExportNamedSpecifier, _: &dyn Node) {
+        self.0.insert(s.orig.sym.to_string());
+    }
+}
+
+fn is_type(decl: &Decl) -> bool {
+    matches!(decl, Decl::TsInterface(_) | Decl::TsTypeAlias(_) | Decl::TsEnum(_) | Decl::TsModule(_))
+}
+
+fn declare_type(decl: Decl) -> Decl {
+    match decl {
+        Decl::TsEnum(mut e) => { e.declare = true; Decl::TsEnum(e) }
+        Decl::TsModule(mut m) => { m.declare = true; Decl::TsModule(m) }
+        decl => decl,
+    }
+}
+
+struct Declarations<'a> {
+    handler: &'a Handler,
+    /// Functions with overload signatures.
+    overloaded: FxHashSet<String>,
+}
+
+impl Declarations<'_> {
+    fn missing(&self, span: Span, what: &str) {
+        self.handler.struct_span_err(span, &format!("{} needs an explicit type annotation for declaration emit", what)).emit();
+    }
+
+    fn pat(&self, pat: Pat) -> Pat {
+        match pat {
+            Pat::Assign(AssignPat { left, right, type_ann, .. }) => match *left {
+                Pat::Ident(BindingIdent { mut id, type_ann: ann }) => {
+                    id.optional = true;
+                    let type_ann = ann.or(type_ann).or_else(|| literal_type(&right, true));
+                    if type_ann.is_none() {
+                        self.missing(id.span, &format!("parameter `{}`", id.sym));
+                    }
+                    Pat::Ident(BindingIdent { id, type_ann })
+                }
+                left => self.pat(left),
+            },
+            pat => {
+                if !annotated(&pat) {
+                    self.missing(pat.span(), "parameter");
+                }
+                pat
+            }
+        }
+    }
+
+    fn param(&self, param: Param) -> Param {
+        Param { pat: self.pat(param.pat), decorators: vec![], ..param }
+    }
+
+    /// Splits a parameter property, which only a constructor implementation may have, into a plain
+    /// parameter and the property it declares.
+    fn param_prop(&self, prop: TsParamProp) -> (Param, Option<ClassMember>) {
+        let (pat, is_optional) = match prop.param {
+            TsParamPropParam::Ident(binding) => (Pat::Ident(binding.clone()), binding.id.optional),
+            TsParamPropParam::Assign(assign) => (Pat::Assign(assign), false),
+        };
+        let pat = self.pat(pat);
+        let member = match &pat {
+            Pat::Ident(BindingIdent { id, type_ann }) => Some(ClassMember::ClassProp(ClassProp {
+                span: prop.span,
+                key: Box::new(Expr::Ident(Ident { optional: is_optional, ..id.clone() })),
+                value: None,
+                type_ann: match prop.accessibility {
+                    Some(Accessibility::Private) => None,
+                    _ => type_ann.clone(),
+                },
+                is_static: false,
+                decorators: vec![],
+                computed: false,
+                accessibility: prop.accessibility,
+                is_abstract: false,
+                is_optional,
+                is_override: prop.is_override,
+                readonly: prop.readonly,
+                declare: false,
+                definite: false,
+            })),
+            _ => None,
+        };
+        (Param { span: prop.span, decorators: vec![], pat }, member)
+    }
+
+    fn function(&self, function: Function, what: &str, needs_return: bool) -> Function {
+        if needs_return && function.return_type.is_none() {
+            self.missing(function.span, &format!("return type of {}", what));
+        }
+        Function {
+            params: function.params.into_iter().map(|p| self.param(p)).collect(),
+            decorators: vec![],
+            body: None,
+            is_async: false,
+            is_generator: false,
+            ..function
+        }
+    }
+
+    /// Keeps the signatures of public and protected members. Private ones stay as `private m;`
+    /// and ES private names as a single `#private;`, since they still make the class nominal.
+    fn class(&self, class: Class) -> Class {
+        let overloaded: FxHashSet<_> = class.body.iter().filter_map(|member| match member {
+            ClassMember::Method(m) if m.function.body.is_none() => key(&m.key),
+            _ => None,
+        }).collect();
+        let mut private = FxHashSet::default();
+        let mut es_private = None;
+        let mut param_props = vec![];
+        let body = class.body.into_iter().filter_map(|member| match member {
+            ClassMember::Constructor(c) => Some(ClassMember::Constructor(Constructor {
+                params: c.params.into_iter().map(|p| ParamOrTsParamProp::Param(match p {
+                    ParamOrTsParamProp::Param(p) => self.param(p),
+                    ParamOrTsParamProp::TsParamProp(prop) => {
+                        let (param, member) = self.param_prop(prop);
+                        param_props.extend(member);
+                        param
+                    }
+                })).collect(),
+                body: None,
+                ..c
+            })),
+            ClassMember::Method(m) if m.accessibility == Some(Accessibility::Private) => {
+                let (key, computed) = match m.key {
+                    PropName::Ident(ident) => (Expr::Ident(ident), false),
+                    PropName::Str(s) => (Expr::Lit(Lit::Str(s)), false),
+                    PropName::Num(n) => (Expr::Lit(Lit::Num(n)), false),
+                    PropName::BigInt(n) => (Expr::Lit(Lit::BigInt(n)), false),
+                    PropName::Computed(c) => (*c.expr, true),
+                };
+                // Overloads of a private method collapse into one member.
+                if let Expr::Ident(Ident { sym, .. }) = &key {
+                    if !private.insert((sym.to_string(), m.is_static)) {
+                        return None;
+                    }
+                }
+                Some(ClassMember::ClassProp(ClassProp {
+                    span: m.span,
+                    key: Box::new(key),
+                    value: None,
+                    type_ann: None,
+                    is_static: m.is_static,
+                    decorators: vec![],
+                    computed,
+                    accessibility: m.accessibility,
+                    is_abstract: false,
+                    is_optional: false,
+                    is_override: false,
+                    readonly: false,
+                    declare: false,
+                    definite: false,
+                }))
+            }
+            // The implementation of an overloaded method isn't part of its type.
+            ClassMember::Method(m) if m.function.body.is_some() && key(&m.key).is_some_and(|k| overloaded.contains(&k)) => None,
+            ClassMember::Method(m) => {
+                let needs_return = m.kind != MethodKind::Setter;
+                Some(ClassMember::Method(ClassMethod { function: self.function(m.function, "method", needs_return), ..m }))
+            }
+            ClassMember::ClassProp(p) if p.accessibility == Some(Accessibility::Private) =>
+                Some(ClassMember::ClassProp(ClassProp { type_ann: None, value: None, decorators: vec![], ..p })),
+            ClassMember::ClassProp(p) => {
+                let inferred = p.value.as_deref().and_then(|v| literal_type(v, !p.readonly));
+                let type_ann = p.type_ann.or(inferred);
+                if type_ann.is_none() {
+                    self.missing(p.span, "property");
+                }
+                Some(ClassMember::ClassProp(ClassProp { type_ann, value: None, decorators: vec![], ..p }))
+            }
+            ClassMember::PrivateMethod(m) => { es_private.get_or_insert(m.span); None }
+            ClassMember::PrivateProp(p) => { es_private.get_or_insert(p.span); None }
+            member => Some(member),
+        });
+        let mut body: Vec<_> = body.collect();
+        // Declared where the constructor is, as `tsc` does.
+        let at = body.iter().position(|member| matches!(member, ClassMember::Constructor(_))).unwrap_or(0);
+        body.splice(at..at, param_props);
+        if let Some(span) = es_private {
+            body.insert(0, ClassMember::PrivateProp(PrivateProp {
+                span,
+                key: PrivateName { span, id: quote_ident!("private") },
+                value: None,
+                type_ann: None,
+                is_static: false,
+                decorators: vec![],
+                computed: false,
+                accessibility: None,
+                is_abstract: false,
+                is_optional: false,
+                is_override: false,
+                readonly: false,
+                definite: false,
+            }));
+        }
+        Class { body, decorators: vec![], ..class }
+    }
+
+    fn var(&self, var: VarDecl) -> VarDecl {
+        let widen = var.kind != VarDeclKind::Const;
+        let decls = var.decls.into_iter().map(|d| {
+            let name = match d.name {
+                Pat::Ident(BindingIdent { id, type_ann: None }) => {
+                    let type_ann = d.init.as_deref().and_then(|init| literal_type(init, widen));
+                    if type_ann.is_none() {
+                        self.missing(id.span, &format!("variable `{}`", id.sym));
+                    }
+                    Pat::Ident(BindingIdent { id, type_ann })
+                }
+                name => {
+                    if !annotated(&name) {
+                        self.missing(name.span(), "variable");
+                    }
+                    name
+                }
+            };
+            VarDeclarator { name, init: None, definite: false, ..d }
+        }).collect();
+        VarDecl { declare: true, decls, ..var }
+    }
+
+    /// `None` for the implementation of an overloaded function, which isn't part of its type.
+    fn decl(&self, decl: Decl) -> Option<Decl> {
+        Some(match decl {
+            Decl::Fn(FnDecl { ident, function, .. }) if function.body.is_some() && self.overloaded.contains(&*ident.sym) => return None,
+            Decl::Fn(FnDecl { ident, function, .. }) => {
+                let function = self.function(function, &format!("function `{}`", ident.sym), true);
+                Decl::Fn(FnDecl { ident, declare: true, function })
+            }
+            Decl::Class(ClassDecl { ident, class, .. }) => Decl::Class(ClassDecl { ident, declare: true, class: self.class(class) }),
+            Decl::Var(var) => Decl::Var(self.var(var)),
+            decl => declare_type(decl),
+        })
+    }
+
+    fn module_decl(&self, decl: ModuleDecl) -> Option<ModuleDecl> {
+        match decl {
+            ModuleDecl::ExportDecl(ExportDecl { span, decl }) => Some(ModuleDecl::ExportDecl(ExportDecl { span, decl: self.decl(decl)? })),
+            ModuleDecl::ExportDefaultDecl(ExportDefaultDecl { span, decl }) => {
+                let decl = match decl {
+                    DefaultDecl::Fn(FnExpr { ident, function }) => DefaultDecl::Fn(FnExpr { ident, function: self.function(function, "default export", true) }),
+                    DefaultDecl::Class(ClassExpr { ident, class }) => DefaultDecl::Class(ClassExpr { ident, class: self.class(class) }),
+                    decl => decl,
+                };
+                Some(ModuleDecl::ExportDefaultDecl(ExportDefaultDecl { span, decl }))
+            }
+            ModuleDecl::ExportDefaultExpr(ExportDefaultExpr { span, expr }) => {
+                if let Expr::Ident(_) = &*expr {
+                    return Some(ModuleDecl::ExportDefaultExpr(ExportDefaultExpr { span, expr }));
+                }
+                self.missing(span, "default export expression");
+                None
+            }
+            decl => Some(decl),
+        }
+    }
+
+    fn item(&self, item: ModuleItem) -> Option<ModuleItem> {
+        match item {
+            ModuleItem::ModuleDecl(decl) => self.module_decl(decl).map(ModuleItem::ModuleDecl),
+            ModuleItem::Stmt(Stmt::Decl(decl)) if is_type(&decl) => Some(ModuleItem::Stmt(Stmt::Decl(declare_type(decl)))),
+            ModuleItem::Stmt(_) => None,
+        }
+    }
+}
+
+/// Emits the `.d.ts` for `input`. Fails with `Error::DiagnosticEmitted` when an export needs
+/// type inference.
+pub fn declarations(filename: &str, input: &str) -> Result<String> {
+    swc_common::GLOBALS.set(&swc_common::Globals::new(), || {
+        let cm: Lrc<SourceMap> = Default::default();
+        let handler = Handler::with_tty_emitter(ColorConfig::Auto, true, false, Some(cm.clone()));
+        let comments = SingleThreadedComments::default();
+
+        let module = parse(&cm, &handler, &comments, filename, input)?;
+        let overloaded = module.body.iter().filter_map(|item| match item {
+            ModuleItem::Stmt(Stmt::Decl(Decl::Fn(f))) | ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { decl: Decl::Fn(f), .. }))
+                if f.function.body.is_none() => Some(f.ident.sym.to_string()),
+            _ => None,
+        }).collect();
+        let declarations = Declarations { handler: &handler, overloaded };
+
+        // The exported API first, then the local declarations it refers to, until no new ones
+        // turn up; the rest of the locals are implementation.
+        let mut items = vec![None; module.body.len()];
+        let mut locals = vec![];
+        for (i, item) in module.body.into_iter().enumerate() {
+            match item {
+                ModuleItem::Stmt(Stmt::Decl(decl)) if !is_type(&decl) => locals.push((i, decl)),
+                item => items[i] = declarations.item(item),
+            }
+        }
+        let mut refs = Refs::default();
+        items.iter().flatten().for_each(|item| item.visit_with(&Invalid { span: DUMMY_SP } as _, &mut refs));
+        loop {
+            let (used, rest): (Vec<_>, Vec<_>) = locals.into_iter().partition(|(_, decl)| names(decl).iter().any(|name| refs.0.contains(name)));
+            if used.is_empty() {
+                break;
+            }
+            locals = rest;
+            for (i, decl) in used {
+                let item = declarations.decl(decl).map(|decl| ModuleItem::Stmt(Stmt::Decl(decl)));
+                item.iter().for_each(|item| item.visit_with(&Invalid { span: DUMMY_SP } as _, &mut refs));
+                items[i] = item;
+            }
+        }
+        let module = Module { body: items.into_iter().flatten().collect(), ..module };
+        if handler.has_errors() {
+            return Err(Error::DiagnosticEmitted);
+        }
+
+        emit(cm, &comments, &[], &module, None, &Options { comments: CommentMode::All, ..Default::default() })
+    })
+}
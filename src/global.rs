@@ -0,0 +1,111 @@
+//! The global scope twasm runs in, either a page or a Web Worker. Compiling needs no DOM at all;
+//! caching goes through whichever scope has `caches`, and evaluation goes through the configured
+//! `Evaluator`.
+
+use js_sys::{Array, Function, Promise, Reflect};
+use wasm_bindgen::{JsCast, prelude::*};
+use web_sys::{Blob, BlobPropertyBag, CacheStorage, Document, HtmlScriptElement, Url, Window, WorkerGlobalScope};
+
+use crate::{Error, Evaluator, Options, Result};
+
+pub(crate) enum Scope {
+    Window(Window),
+    Worker(WorkerGlobalScope),
+}
+
+pub(crate) fn scope() -> Result<Scope> {
+    let global = js_sys::global();
+    if let Some(window) = global.dyn_ref::<Window>() {
+        return Ok(Scope::Window(window.clone()));
+    }
+    global.dyn_into::<WorkerGlobalScope>().map(Scope::Worker).map_err(|_| Error::InvalidScope)
+}
+
+impl Scope {
+    pub fn caches(&self) -> Result<CacheStorage> {
+        Ok(match self {
+            Scope::Window(window) => window.caches()?,
+            Scope::Worker(worker) => worker.caches()?,
+        })
+    }
+
+    pub fn fetch(&self, path: &str) -> Promise {
+        match self {
+            Scope::Window(window) => window.fetch_with_str(path),
+            Scope::Worker(worker) => worker.fetch_with_str(path),
+        }
+    }
+
+    fn nonce(document: &Document, options: &Options) -> Option<String> {
+        options.nonce.clone().or_else(|| {
+            // Browsers hide the attribute once parsed; the property keeps the value.
+            let script = document.query_selector("script[nonce]").ok()??;
+            Reflect::get(&script, &"nonce".into()).ok()?.as_string().filter(|n| !n.is_empty())
+        })
+    }
+
+    fn inject(window: &Window, options: &Options, init: impl FnOnce(&HtmlScriptElement) -> Result<()>) -> Result<HtmlScriptElement> {
+        let document = window.document().ok_or(Error::InvalidDocument)?;
+        let head = document.head().ok_or(Error::InvalidHead)?;
+        let script: HtmlScriptElement = document.create_element("script")?.unchecked_into();
+        if let Some(nonce) = Scope::nonce(&document, options) {
+            script.set_attribute("nonce", &nonce)?;
+        }
+        init(&script)?;
+        head.append_child(&script)?;
+        Ok(script)
+    }
+
+    /// Runs a classic script in the global scope, named `path` in stack traces.
+    pub fn run(&self, code: &str, path: &str, options: &Options) -> Result<()> {
+        let code = format!("{}\n//# sourceURL={}", code, path);
+        // `createPolicy` throws when the CSP disallows or already used the policy name.
+        let trusted = |kind: &str, value: &str| crate::runtime::trusted(options.trusted_types.clone(), kind, value).map_err(Error::JSError);
+        let global = js_sys::global();
+        let evaluator = match (options.evaluator, self) {
+            (Evaluator::Script, Scope::Worker(_)) => Evaluator::Function,
+            (evaluator, _) => evaluator,
+        };
+        match evaluator {
+            Evaluator::Function => {
+                let constructor: Function = Reflect::get(&global, &"Function".into())?.dyn_into()?;
+                let function: Function = Reflect::construct(&constructor, &Array::of1(&trusted("script", &code)?))?.dyn_into()?;
+                function.call0(&JsValue::UNDEFINED)?;
+            }
+            Evaluator::Eval => {
+                let eval: Function = Reflect::get(&global, &"eval".into())?.dyn_into()?;
+                eval.call1(&JsValue::UNDEFINED, &trusted("script", &code)?)?;
+            }
+            Evaluator::Script => if let Scope::Window(window) = self {
+                let script = Scope::inject(window, options, |script| { Reflect::set(script, &"text".into(), &trusted("script", &code)?)?; Ok(()) })?;
+                script.remove();
+            },
+            Evaluator::Blob => {
+                let mut blob_options = BlobPropertyBag::new();
+                blob_options.type_("text/javascript");
+                let blob = Blob::new_with_str_sequence_and_options(&Array::of1(&code.into()), &blob_options)?;
+                let url = Url::create_object_url_with_blob(&blob)?;
+                crate::runtime::script(&url, path);
+                match self {
+                    Scope::Window(window) => {
+                        let script = Scope::inject(window, options, |script| { Reflect::set(script, &"src".into(), &trusted("url", &url)?)?; Ok(()) })?;
+                        let (elem, src) = (script.clone(), url.clone());
+                        let done = Closure::once_into_js(move || {
+                            elem.remove();
+                            Url::revoke_object_url(&src).ok();
+                        });
+                        script.set_onload(Some(done.unchecked_ref()));
+                        script.set_onerror(Some(done.unchecked_ref()));
+                    }
+                    // Module workers have no `importScripts`; use a classic worker for blob evaluation.
+                    Scope::Worker(worker) => {
+                        let result = Reflect::get(worker, &"importScripts".into())?.dyn_into::<Function>()?.call1(worker, &trusted("url", &url)?);
+                        Url::revoke_object_url(&url).ok();
+                        result?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
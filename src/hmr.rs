@@ -0,0 +1,78 @@
+//! Hot module replacement client. The dev server pushes `{"type":"update","path":"..."}` messages
+//! over a WebSocket; a module that calls `import.meta.hot.accept` is recompiled and re-evaluated in
+//! place, anything else falls back to a full page reload.
+
+use js_sys::{JSON, Reflect};
+use swc_ecma_ast::{CallExpr, Expr, Lit, MetaPropExpr};
+use swc_ecma_utils::{ExprFactory, quote_ident, quote_str};
+use swc_ecma_visit::{Fold, FoldWith, noop_fold_type};
+use wasm_bindgen::{JsCast, prelude::*};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{MessageEvent, WebSocket};
+
+use crate::{Error, Result, overlay, prefetch, runtime};
+
+struct ImportMeta(String);
+impl Fold for ImportMeta {
+    noop_fold_type!();
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        match expr {
+            Expr::MetaProp(MetaPropExpr { meta, prop }) if &*meta.sym == "import" && &*prop.sym == "meta" => Expr::Call(CallExpr {
+                span: meta.span,
+                callee: quote_ident!("__twasm_meta").as_callee(),
+                args: vec![Expr::Lit(Lit::Str(quote_str!(self.0.clone()))).as_arg()],
+                type_args: None,
+            }),
+            _ => expr.fold_children_with(self),
+        }
+    }
+}
+
+/// Rewrites `import.meta` to the runtime's meta object for the module at `path`, which carries
+/// `import.meta.hot`. Keyed by path rather than keyid so compiled output stays cacheable.
+pub(crate) fn import_meta(path: &str) -> impl Fold { ImportMeta(path.to_string()) }
+
+fn update_path(data: &str) -> Option<String> {
+    let msg = JSON::parse(data).ok()?;
+    match Reflect::get(&msg, &"type".into()).ok()?.as_string()?.as_str() {
+        "update" => Reflect::get(&msg, &"path".into()).ok()?.as_string(),
+        _ => None,
+    }
+}
+
+async fn update(path: &str) -> Result<()> {
+    let window = web_sys::window().ok_or(Error::InvalidWindow)?;
+    let old = match runtime::lookup(path) {
+        Some(key) => key,
+        // A module that failed to compile never got a key; reload to retry it and clear the overlay.
+        None if overlay::visible() => return Ok(window.location().reload()?),
+        None => return Ok(()),
+    };
+    if !runtime::dispose(old) {
+        return Ok(window.location().reload()?);
+    }
+    prefetch::invalidate(path);
+    let input = prefetch::fetch(path).await?;
+    let keyid = crate::load(path, &input).await?;
+    runtime::accept(old, keyid as f64);
+    overlay::hide();
+    Ok(())
+}
+
+#[wasm_bindgen(js_name = hmrConnect)]
+pub fn hmr_connect(url: &str) -> std::result::Result<(), JsValue> {
+    let ws = WebSocket::new(url)?;
+    let onmessage = Closure::wrap(Box::new(|e: MessageEvent| {
+        if let Some(path) = e.data().as_string().as_deref().and_then(update_path) {
+            spawn_local(async move {
+                if let Err(e) = update(&path).await {
+                    console_log!("hot update of {} failed: {:?}", path, e);
+                }
+            });
+        }
+    }) as Box<dyn FnMut(MessageEvent)>);
+    ws.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+    onmessage.forget();
+    Ok(())
+}
@@ -0,0 +1,146 @@
+//! `isolatedModules` checks. Every file is compiled on its own, so constructs whose output depends
+//! on type information from other files silently turn into `undefined` at runtime; this pass
+//! reports the ones that can be detected from a single file before they are stripped.
+
+use fxhash::FxHashSet;
+use swc_common::{DUMMY_SP, Span, errors::Handler};
+use swc_ecma_ast::{
+    Decl, ExportDecl, ExportSpecifier, Expr, Ident, ImportSpecifier, Invalid, JSXElementName, JSXObject, Module, ModuleDecl,
+    ModuleItem, Prop, Stmt, TsEntityName, TsModuleName, TsTypeQuery,
+};
+use swc_ecma_utils::DestructuringFinder;
+use swc_ecma_visit::{Node, Visit, VisitWith};
+
+/// Names read as values and names referenced as types, anywhere in the module.
+#[derive(Default)]
+struct Uses {
+    values: FxHashSet<String>,
+    types: FxHashSet<String>,
+}
+
+impl Visit for Uses {
+    fn visit_expr(&mut self, expr: &Expr, _: &dyn Node) {
+        if let Expr::Ident(ident) = expr {
+            self.values.insert(ident.sym.to_string());
+        }
+        expr.visit_children_with(self);
+    }
+
+    /// `{ x }` reads `x`.
+    fn visit_prop(&mut self, prop: &Prop, _: &dyn Node) {
+        if let Prop::Shorthand(ident) = prop {
+            self.values.insert(ident.sym.to_string());
+        }
+        prop.visit_children_with(self);
+    }
+
+    /// `<Foo />` and `<foo.Bar />` read `Foo` and `foo`; lowercase `<div />` is an intrinsic element.
+    fn visit_jsx_element_name(&mut self, name: &JSXElementName, _: &dyn Node) {
+        let mut object = match name {
+            JSXElementName::Ident(ident) if !ident.sym.starts_with(|c: char| c.is_ascii_lowercase()) => {
+                self.values.insert(ident.sym.to_string());
+                return;
+            }
+            JSXElementName::JSXMemberExpr(member) => &member.obj,
+            _ => return,
+        };
+        loop {
+            match object {
+                JSXObject::Ident(ident) => { self.values.insert(ident.sym.to_string()); return; }
+                JSXObject::JSXMemberExpr(member) => object = &member.obj,
+            }
+        }
+    }
+
+    fn visit_ts_entity_name(&mut self, name: &TsEntityName, _: &dyn Node) {
+        // `ns.T` uses the namespace `ns` as a value.
+        if let TsEntityName::Ident(ident) = name {
+            self.types.insert(ident.sym.to_string());
+        }
+    }
+
+    /// `typeof x` reads the value `x`.
+    fn visit_ts_type_query(&mut self, query: &TsTypeQuery, _: &dyn Node) {
+        let mut values = Uses::default();
+        query.visit_children_with(&mut values);
+        self.values.extend(values.types);
+    }
+}
+
+/// Emits a diagnostic for every violation and returns whether the module is clean.
+pub(crate) fn check(module: &Module, handler: &Handler) -> bool {
+    let mut types = FxHashSet::default();
+    let mut values = FxHashSet::default();
+    let mut imported = FxHashSet::default();
+    let mut errors = 0;
+    let mut error = |span: Span, msg: String| {
+        handler.struct_span_err(span, &msg).emit();
+        errors += 1;
+    };
+
+    for item in &module.body {
+        let (decl, exported) = match item {
+            ModuleItem::Stmt(Stmt::Decl(decl)) => (decl, false),
+            ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { decl, .. })) => (decl, true),
+            ModuleItem::ModuleDecl(ModuleDecl::Import(import)) if !import.type_only => {
+                imported.extend(import.specifiers.iter().map(|s| match s {
+                    ImportSpecifier::Named(s) => s.local.sym.to_string(),
+                    ImportSpecifier::Default(s) => s.local.sym.to_string(),
+                    ImportSpecifier::Namespace(s) => s.local.sym.to_string(),
+                }));
+                continue;
+            }
+            _ => continue,
+        };
+        match decl {
+            Decl::TsInterface(i) => { types.insert(i.id.sym.to_string()); }
+            Decl::TsTypeAlias(t) => { types.insert(t.id.sym.to_string()); }
+            Decl::TsEnum(e) => {
+                values.insert(e.id.sym.to_string());
+                if e.is_const && (exported || e.declare) {
+                    error(e.span, format!("const enum `{}` can't be inlined into other files when modules are compiled in isolation; use a regular enum", e.id.sym));
+                }
+            }
+            Decl::TsModule(m) => if let TsModuleName::Ident(id) = &m.id { values.insert(id.sym.to_string()); },
+            Decl::Fn(f) => { values.insert(f.ident.sym.to_string()); }
+            Decl::Class(c) => { values.insert(c.ident.sym.to_string()); }
+            Decl::Var(var) => {
+                let mut found: Vec<Ident> = vec![];
+                var.decls.visit_with(&Invalid { span: DUMMY_SP } as _, &mut DestructuringFinder { found: &mut found });
+                values.extend(found.into_iter().map(|i| i.sym.to_string()));
+            }
+        }
+    }
+
+    // Whether an import is a type is only known to the file it comes from, but one this file
+    // itself uses as a type and never as a value almost certainly is.
+    let mut uses = Uses::default();
+    module.visit_with(&Invalid { span: DUMMY_SP } as _, &mut uses);
+    let type_only = |ident: &Ident| match imported.contains(&*ident.sym) {
+        true => uses.types.contains(&*ident.sym) && !uses.values.contains(&*ident.sym),
+        false => types.contains(&*ident.sym) && !values.contains(&*ident.sym),
+    };
+    for item in &module.body {
+        match item {
+            ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(export)) if export.src.is_none() && !export.type_only => {
+                for s in &export.specifiers {
+                    if let ExportSpecifier::Named(s) = s {
+                        if type_only(&s.orig) {
+                            error(s.span, format!("`{}` is a type and must be re-exported with `export type` when modules are compiled in isolation", s.orig.sym));
+                        }
+                    }
+                }
+            }
+            ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultExpr(export)) => {
+                if let Expr::Ident(ident) = &*export.expr {
+                    if type_only(ident) {
+                        error(export.span, format!("`{}` is a type and can't be a default export value when modules are compiled in isolation", ident.sym));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    errors == 0
+}
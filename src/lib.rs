@@ -11,11 +11,14 @@ pub fn set_panic_hook() {
 
 use std::{io::Write, path::PathBuf, sync::{Arc, RwLock}};
 use swc_ecma_parser::{Capturing, JscTarget, Parser, StringInput, Syntax, TsConfig, lexer::Lexer};
-use swc_common::{FileName, SourceMap, errors::{ColorConfig, Handler}, sync::Lrc};
+use swc_common::{BytePos, FileName, LineCol, Mark, SourceMap, Spanned, comments::{Comment, CommentKind, Comments, SingleThreadedComments}, errors::Handler, sync::Lrc};
+use swc_ecma_ast::{Module, ModuleDecl, ModuleItem};
 use swc_ecma_codegen::{Emitter, text_writer::JsWriter};
+use swc_ecma_minifier::option::{ExtraOptions, MinifyOptions};
+use swc_ecma_transforms_base::{fixer::fixer, helpers::{HELPERS, Helpers, inject_helpers}, hygiene::hygiene, resolver::resolver_with_mark};
 
 use swc_ecma_visit::FoldWith;
-use wasm_bindgen::prelude::*;
+use wasm_bindgen::{JsCast, prelude::*};
 
 #[cfg(feature = "wee_alloc")]
 #[global_allocator]
@@ -25,29 +28,89 @@ static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 extern "C" {
     #[wasm_bindgen(js_namespace = console)]
     fn log(s: &str);
+    #[wasm_bindgen(js_namespace = console)]
+    fn error(s: &str);
     fn alert(s: &str);
     fn eval(s: &str);
 }
 
-macro_rules! console_log { ($($t:tt)*) => (#[allow(unused_unsafe)] unsafe { log(&format_args!($($t)*).to_string()) }) }
+macro_rules! console_log { ($($t:tt)*) => (#[allow(unused_unsafe)] unsafe { $crate::log(&format_args!($($t)*).to_string()) }) }
+
+mod api;
+pub mod cache;
+mod define;
+pub mod deps;
+pub mod dts;
+mod global;
+mod hmr;
+mod isolated;
+pub mod loader;
+pub mod module;
+mod options;
+mod overlay;
+pub mod plugin;
+mod pool;
+mod prefetch;
+pub mod report;
+pub mod resolve;
+mod scripts;
+pub mod vfs;
+pub mod bundle;
+
+pub use options::{CommentMode, Evaluator, ModuleFormat, Options};
 
 #[derive(Debug)]
-enum Error {
+pub enum Error {
     JSError(JsValue),
     ECMAParseError(swc_ecma_parser::error::Error),
     IOError(std::io::Error),
     PoisonError(String),
+    SourceMapError(sourcemap::Error),
     DiagnosticEmitted,
     InvalidWindow,
+    InvalidScope,
+    InvalidWorkerReply,
     InvalidDocument,
     InvalidHead,
+    /// Evaluating the module at the path threw.
+    EvalError(String, JsValue),
+    /// What `compile` reported before failing with `DiagnosticEmitted`.
+    Diagnostics(Vec<report::Diagnostic>),
+    /// No source loader had the path.
+    NotFound(String),
+    SerdeError(serde_json::Error),
+    /// The `define` value for the key is neither JSON nor a dotted identifier.
+    InvalidDefine(String),
+    /// The module at the path has no `define` or `System.register` call to evaluate it through, as
+    /// with the CommonJS and ES formats.
+    Unregistered(String),
 }
 impl From<JsValue> for Error { fn from(e: JsValue) -> Error { Error::JSError(e) } }
 impl From<std::io::Error> for Error { fn from(e: std::io::Error) -> Error { Error::IOError(e) } }
 impl From<swc_ecma_parser::error::Error> for Error { fn from(e: swc_ecma_parser::error::Error) -> Error { Error::ECMAParseError(e) } }
+impl From<sourcemap::Error> for Error { fn from(e: sourcemap::Error) -> Error { Error::SourceMapError(e) } }
+impl From<serde_json::Error> for Error { fn from(e: serde_json::Error) -> Error { Error::SerdeError(e) } }
+/// JS errors pass through and diagnostics become `{ message, diagnostics }`, so they survive the trip
+/// from a compiler worker; anything else is its `Debug` string.
+impl From<Error> for JsValue {
+    fn from(e: Error) -> JsValue {
+        match e {
+            Error::JSError(e) | Error::EvalError(_, e) => e,
+            Error::Diagnostics(diagnostics) => {
+                let message = diagnostics.iter().map(|d| d.message.as_str()).collect::<Vec<_>>().join("\n");
+                let obj = js_sys::Object::new();
+                js_sys::Reflect::set(&obj, &"message".into(), &message.into()).ok();
+                js_sys::Reflect::set(&obj, &"diagnostics".into(), &serde_json::to_string(&diagnostics).ok()
+                    .and_then(|json| js_sys::JSON::parse(&json).ok()).unwrap_or(JsValue::NULL)).ok();
+                obj.into()
+            }
+            e => JsValue::from_str(format!("{:?}", e).as_str()),
+        }
+    }
+}
 impl<T> From<std::sync::PoisonError<T>> for Error { fn from(e: std::sync::PoisonError<T>) -> Error { Error::PoisonError(e.to_string()) } }
 
-type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = std::result::Result<T, Error>;
 
 mod keyid {
     use std::sync::atomic::{AtomicU64, Ordering};
@@ -55,6 +118,22 @@ mod keyid {
     pub fn new() -> u64 { KEYID.fetch_add(1, Ordering::SeqCst) }
 }
 
+mod runtime {
+    use wasm_bindgen::prelude::*;
+    #[wasm_bindgen(module = "/src/runtime.js")]
+    extern "C" {
+        pub fn install(load: &js_sys::Function);
+        pub fn exports(key: f64) -> js_sys::Promise;
+        pub fn register(key: f64, path: &str);
+        pub fn script(url: &str, path: &str);
+        #[wasm_bindgen(catch)]
+        pub fn trusted(policy: Option<String>, kind: &str, value: &str) -> Result<JsValue, JsValue>;
+        pub fn lookup(path: &str) -> Option<f64>;
+        pub fn dispose(key: f64) -> bool;
+        pub fn accept(old: f64, key: f64);
+    }
+}
+
 #[derive(Debug, Clone)]
 struct Buf(Arc<RwLock<Vec<u8>>>);
 impl Write for Buf {
@@ -67,78 +146,249 @@ impl Write for Buf {
     }
 }
 
-fn transpile(filename: &str, input: &str) -> Result<u64> {
-    swc_common::GLOBALS.set(&swc_common::Globals::new(), || {
-        let cm: Lrc<SourceMap> = Default::default();
-        let handler = Handler::with_tty_emitter(ColorConfig::Auto, true, false, Some(cm.clone()));
-
-        let source = cm.new_source_file(
-            FileName::Real(PathBuf::from(filename)),
-            input.to_owned(),
-        );
-
-        let lexer = Lexer::new(
-            Syntax::Typescript(TsConfig {
-                dts: filename.ends_with(".d.ts"),
-                tsx: filename.contains("tsx"),
-                dynamic_import: true,
-                decorators: true,
-                import_assertions: true,
-                no_early_errors: false,
-                ..Default::default()
-            }),
-            JscTarget::Es2016,
-            StringInput::from(&*source),
-            None,
-        );
-
-        let capturing = Capturing::new(lexer);
-
-        let mut parser = Parser::new_from(capturing);
-        for e in parser.take_errors() {
-            e.into_diagnostic(&handler).emit();
+fn parse(cm: &Lrc<SourceMap>, handler: &Handler, comments: &SingleThreadedComments, filename: &str, input: &str) -> Result<Module> {
+    let source = cm.new_source_file(
+        FileName::Real(PathBuf::from(filename)),
+        input.to_owned(),
+    );
+
+    let lexer = Lexer::new(
+        Syntax::Typescript(TsConfig {
+            dts: filename.ends_with(".d.ts"),
+            tsx: filename.contains("tsx"),
+            dynamic_import: true,
+            decorators: true,
+            import_assertions: true,
+            no_early_errors: false,
+        }),
+        JscTarget::Es2016,
+        StringInput::from(&*source),
+        Some(comments),
+    );
+
+    let capturing = Capturing::new(lexer);
+
+    let mut parser = Parser::new_from(capturing);
+    for e in parser.take_errors() {
+        e.into_diagnostic(handler).emit();
+    }
+
+    parser
+        .parse_typescript_module()
+        .map_err(|e| { e.into_diagnostic(handler).emit(); Error::DiagnosticEmitted })
+}
+
+fn minify(module: Module, comments: &SingleThreadedComments) -> Module {
+    let top_level_mark = Mark::fresh(Mark::root());
+    let module = module.fold_with(&mut resolver_with_mark(top_level_mark));
+    swc_ecma_minifier::optimize(
+        module,
+        Some(comments),
+        None,
+        &MinifyOptions {
+            compress: Some(Default::default()),
+            mangle: Some(Default::default()),
+            ..Default::default()
+        },
+        &ExtraOptions { top_level_mark },
+    )
+    .fold_with(&mut hygiene())
+    .fold_with(&mut fixer(Some(comments)))
+}
+
+/// License headers (`/*!`, `@license`, `@preserve`) and `@__PURE__` annotations.
+fn is_legal(comment: &Comment) -> bool {
+    comment.text.starts_with('!')
+        || ["@license", "@preserve", "@__PURE__", "#__PURE__"].iter().any(|tag| comment.text.contains(tag))
+}
+
+/// Moves comments from `export` keywords to the declarations they export, which is what the module
+/// formats keep.
+fn attach_export_comments(module: &Module, comments: &SingleThreadedComments) {
+    for item in &module.body {
+        let (export, decl) = match item {
+            ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(export)) => (export.span, export.decl.span()),
+            ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(export)) => (export.span, export.decl.span()),
+            ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultExpr(export)) => (export.span, export.expr.span()),
+            _ => continue,
+        };
+        if let Some(leading) = comments.take_leading(export.lo) {
+            comments.add_leading_comments(decl.lo, leading);
         }
+    }
+}
 
-        let module = parser
-            .parse_typescript_module()
-            .map_err(|e| { e.into_diagnostic(&handler).emit(); Error::DiagnosticEmitted })?
-            .fold_with(&mut swc_ecma_transforms_typescript::strip())
-            .fold_with(&mut swc_ecma_transforms_module::amd::amd(Default::default()));
-            //.fold_with(&mut swc_ecma_transforms_module::umd::umd(cm.clone(), Mark::fresh(Mark::root()), Default::default()));
-
-        let mut wr = Buf(Arc::new(RwLock::new(vec![])));
-
-        {
-            let mut emitter = Emitter {
-                cfg: Default::default(),
-                cm: cm.clone(),
-                wr: Box::new(JsWriter::new(cm, "\n", &mut wr, None)),
-                comments: None,
-            };
-            emitter.emit_module(&module)?;
+fn retain_comments(comments: &SingleThreadedComments, mode: CommentMode) {
+    if mode != CommentMode::Legal {
+        return;
+    }
+    // The maps are shared, so the clone's are the ones the emitter reads.
+    let (leading, trailing) = comments.clone().take_all();
+    for map in [leading, trailing].iter() {
+        let mut map = map.borrow_mut();
+        map.values_mut().for_each(|list| list.retain(is_legal));
+        map.retain(|_, list| !list.is_empty());
+    }
+}
+
+/// Emits `module` with `header` comments, which `options.comments` filters like the rest, above it.
+fn emit(cm: Lrc<SourceMap>, comments: &SingleThreadedComments, header: &[Comment], module: &Module, srcmap: Option<&mut Vec<(BytePos, LineCol)>>, options: &Options) -> Result<String> {
+    let mut wr = Buf(Arc::new(RwLock::new(vec![])));
+    retain_comments(comments, options.comments);
+
+    {
+        let mut emitter = Emitter {
+            cfg: swc_ecma_codegen::Config { minify: options.minify },
+            cm: cm.clone(),
+            wr: Box::new(JsWriter::new(cm, "\n", &mut wr, srcmap)),
+            comments: match options.comments {
+                CommentMode::None => None,
+                _ => Some(comments as &dyn Comments),
+            },
         };
+        let header = header.iter().filter(|comment| match options.comments {
+            CommentMode::None => false,
+            CommentMode::All => true,
+            CommentMode::Legal => is_legal(comment),
+        });
+        for comment in header {
+            match comment.kind {
+                CommentKind::Line => emitter.wr.write_comment(comment.span, &format!("//{}", comment.text))?,
+                CommentKind::Block => emitter.wr.write_comment(comment.span, &format!("/*{}*/", comment.text))?,
+            }
+            emitter.wr.write_line()?;
+        }
+        emitter.emit_module(module)?;
+    };
 
-        let code_output = wr.0.read()?;
-        let output = &*String::from_utf8_lossy(&code_output);
+    let code_output = wr.0.read()?;
+    Ok(String::from_utf8_lossy(&code_output).to_string())
+}
+
+/// Output of compiling one module: the script in the configured module format and its source map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Compiled {
+    pub code: String,
+    pub map: String,
+}
 
-        let keyid = keyid::new();
+pub fn compile(filename: &str, input: &str, options: &Options) -> Result<Compiled> {
+    swc_common::GLOBALS.set(&swc_common::Globals::new(), || {
+        let cm: Lrc<SourceMap> = Default::default();
+        let (handler, collected) = report::handler(&cm);
+        let comments = SingleThreadedComments::default();
+        let diagnostics = |e: Error| match e {
+            Error::DiagnosticEmitted => Error::Diagnostics(collected.take(&cm)),
+            e => e,
+        };
+
+        let module = parse(&cm, &handler, &comments, filename, input).map_err(diagnostics)?;
+        // The file's leading comments belong to its first item, which the module formats move into
+        // their factory or drop with an import; they are emitted above the module instead.
+        let header = module.body.first().and_then(|item| comments.take_leading(item.span().lo)).unwrap_or_default();
+        attach_export_comments(&module, &comments);
+        if !isolated::check(&module, &handler) {
+            return Err(diagnostics(Error::DiagnosticEmitted));
+        }
+        let module = module.fold_with(&mut swc_ecma_transforms_typescript::strip());
+        let module = define::define(module, &options.define)?;
+        let module = plugin::apply(module, filename)?;
+        // The module passes call interop helpers, e.g. for default imports; they are inlined.
+        let mut module = HELPERS.set(&Helpers::new(false), || match options.module {
+            ModuleFormat::Amd => module
+                .fold_with(&mut hmr::import_meta(filename))
+                .fold_with(&mut swc_ecma_transforms_module::amd::amd(Default::default())),
+            ModuleFormat::Umd => module
+                .fold_with(&mut crate::module::umd::umd(cm.clone(), filename, &options.umd)),
+            // The fold's `_export`, `_context` and setter parameters are private idents, renamed
+            // here where user code binds the same names.
+            ModuleFormat::System => module
+                .fold_with(&mut crate::module::system::system())
+                .fold_with(&mut hygiene()),
+            ModuleFormat::CommonJs => module
+                .fold_with(&mut crate::module::common_js::common_js()),
+            ModuleFormat::Es => module,
+        }.fold_with(&mut inject_helpers()));
+        if options.minify {
+            module = minify(module, &comments);
+        }
 
-        let window = web_sys::window().ok_or(Error::InvalidWindow)?;
-        let document = window.document().ok_or(Error::InvalidDocument)?;
-        let head = document.head().ok_or(Error::InvalidHead)?;
-        let elem = document.create_element("script")?;
-        elem.set_inner_html(format!("define({}, {}", keyid, &output[7..]).as_str());
-        head.append_child(&elem)?;
+        let mut srcmap = vec![];
+        let code = emit(cm.clone(), &comments, &header, &module, Some(&mut srcmap), options)?;
+        let mut map = vec![];
+        cm.build_source_map(&mut srcmap).to_writer(&mut map)?;
 
-        Ok(keyid)
+        Ok(Compiled { code, map: String::from_utf8_lossy(&map).to_string() })
     })
 }
 
-#[wasm_bindgen]
-pub fn main(filename: &str, input: &str) -> std::result::Result<JsValue, JsValue> {
-    match transpile(filename, input) {
-        Err(e) => Err(JsValue::from_str(format!("{:?}", e).as_str())),
-        Ok(keyid) => Ok(JsValue::from_f64(keyid as f64)),
+impl Compiled {
+    fn to_js(&self) -> Result<JsValue> {
+        let obj = js_sys::Object::new();
+        js_sys::Reflect::set(&obj, &"code".into(), &self.code.as_str().into())?;
+        js_sys::Reflect::set(&obj, &"map".into(), &self.map.as_str().into())?;
+        Ok(obj.into())
+    }
+
+    fn from_js(value: &JsValue) -> Option<Compiled> {
+        let field = |name: &str| js_sys::Reflect::get(value, &name.into()).ok().and_then(|v| v.as_string());
+        Some(Compiled { code: field("code")?, map: field("map")? })
+    }
+}
+
+fn evaluate(filename: &str, compiled: &Compiled) -> Result<u64> {
+    let scope = global::scope()?;
+    let keyid = keyid::new();
+
+    let options = options::current();
+    let at = module::registration(&compiled.code, options.module).ok_or_else(|| Error::Unregistered(filename.to_string()))?;
+    runtime::register(keyid as f64, filename);
+    let offset = match options.evaluator {
+        Evaluator::Function => 2,
+        _ => 0,
+    };
+    let key = format!("{}, ", keyid);
+    report::register(filename, &compiled.map, offset, Some(report::Splice::at(&compiled.code, at, &key)));
+    let code = [&compiled.code[..at], &key, &compiled.code[at..]].concat();
+    scope.run(&code, filename, &options)
+        .map_err(|e| match e {
+            Error::JSError(e) => Error::EvalError(filename.to_string(), e),
+            e => e,
+        })?;
+
+    Ok(keyid)
+}
+
+/// Compiles and evaluates a module, reusing the persistent cache when the same source was already
+/// compiled with the same options. Cache failures (e.g. no Cache Storage outside secure contexts)
+/// only cost the lookup.
+async fn load(filename: &str, input: &str) -> Result<u64> {
+    report::source(filename, input);
+    let options = options::current();
+    let key = cache::key(filename, input, &options);
+    let compiled = match cache::get(&key).await.unwrap_or(None) {
+        Some(compiled) => compiled,
+        None => {
+            let compiled = pool::compile(filename, input, &options).await
+                .inspect_err(overlay::compile_error)?;
+            cache::put(&key, &compiled).await.ok();
+            compiled
+        }
+    };
+    evaluate(filename, &compiled)
+}
+
+#[wasm_bindgen(start)]
+pub fn start() {
+    set_panic_hook();
+    // Node has neither a page nor a worker scope and only uses `compile`; a global `define` there
+    // would send UMD packages down their AMD branch.
+    if global::scope().is_ok() {
+        let loader = api::loader();
+        runtime::install(loader.as_ref().unchecked_ref());
+        loader.forget();
+        report::install().ok();
+        scripts::install().ok();
     }
 }
 
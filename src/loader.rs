@@ -0,0 +1,144 @@
+//! Where module sources come from. The prefetcher asks the current `SourceLoader` for every path
+//! the resolver produces; by default that is the VFS, then `fetch` against the page. Native tools
+//! read from disk, tests serve fixtures, and pages can route requests through a JS callback with
+//! `setLoader`, e.g. to add auth headers.
+
+use std::{cell::RefCell, future::Future, path::PathBuf, pin::Pin, rc::Rc};
+use js_sys::{Function, Promise};
+use wasm_bindgen::{JsCast, prelude::*};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::Response;
+
+use crate::{Error, Result, api::LoaderCallback, global, vfs};
+
+pub type Load<'a> = Pin<Box<dyn Future<Output = Result<String>> + 'a>>;
+
+/// Loads module sources by resolved path. `Error::NotFound` means "not here", and lets a `Chain`
+/// move on to the next loader.
+pub trait SourceLoader {
+    fn load<'a>(&'a self, path: &'a str) -> Load<'a>;
+}
+
+/// `fetch` relative to the page, or to `base` when set.
+#[derive(Debug, Clone, Default)]
+pub struct Fetch {
+    base: Option<String>,
+}
+
+impl Fetch {
+    pub fn new(base: impl Into<String>) -> Fetch {
+        Fetch { base: Some(base.into()) }
+    }
+
+    fn url(&self, path: &str) -> String {
+        match &self.base {
+            Some(base) if !path.contains("://") => format!("{}/{}", base.trim_end_matches('/'), path.trim_start_matches('/')),
+            _ => path.to_string(),
+        }
+    }
+}
+
+impl SourceLoader for Fetch {
+    fn load<'a>(&'a self, path: &'a str) -> Load<'a> {
+        Box::pin(async move {
+            let url = self.url(path);
+            let response: Response = JsFuture::from(global::scope()?.fetch(&url)).await?.dyn_into()?;
+            match response.status() {
+                404 => Err(Error::NotFound(path.to_string())),
+                _ if !response.ok() => Err(JsValue::from_str(&format!("failed to fetch {}: {}", url, response.status())).into()),
+                _ => Ok(JsFuture::from(response.text()?).await?.as_string().unwrap_or_default()),
+            }
+        })
+    }
+}
+
+/// Reads paths below `root`, absolute ones included.
+#[derive(Debug, Clone)]
+pub struct Fs {
+    root: PathBuf,
+}
+
+impl Fs {
+    pub fn new(root: impl Into<PathBuf>) -> Fs {
+        Fs { root: root.into() }
+    }
+}
+
+impl SourceLoader for Fs {
+    fn load<'a>(&'a self, path: &'a str) -> Load<'a> {
+        Box::pin(async move {
+            match std::fs::read_to_string(self.root.join(path.trim_start_matches('/'))) {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(Error::NotFound(path.to_string())),
+                result => Ok(result?),
+            }
+        })
+    }
+}
+
+/// Files written with `vfs::write`.
+#[derive(Debug, Clone, Default)]
+pub struct Vfs;
+
+impl SourceLoader for Vfs {
+    fn load<'a>(&'a self, path: &'a str) -> Load<'a> {
+        Box::pin(async move { vfs::read(path).ok_or_else(|| Error::NotFound(path.to_string())) })
+    }
+}
+
+/// Calls `callback(path)`, which returns the source or a promise of it; `null` or `undefined` for
+/// a missing file.
+#[derive(Debug, Clone)]
+pub struct Callback(pub Function);
+
+impl SourceLoader for Callback {
+    fn load<'a>(&'a self, path: &'a str) -> Load<'a> {
+        Box::pin(async move {
+            let result = self.0.call1(&JsValue::UNDEFINED, &path.into())?;
+            JsFuture::from(Promise::resolve(&result)).await?.as_string().ok_or_else(|| Error::NotFound(path.to_string()))
+        })
+    }
+}
+
+/// Tries each loader in turn until one has the file.
+#[derive(Default)]
+pub struct Chain(pub Vec<Box<dyn SourceLoader>>);
+
+impl SourceLoader for Chain {
+    fn load<'a>(&'a self, path: &'a str) -> Load<'a> {
+        Box::pin(async move {
+            for loader in &self.0 {
+                match loader.load(path).await {
+                    Err(Error::NotFound(_)) => continue,
+                    result => return result,
+                }
+            }
+            Err(Error::NotFound(path.to_string()))
+        })
+    }
+}
+
+fn default() -> Rc<dyn SourceLoader> {
+    Rc::new(Chain(vec![Box::new(Vfs), Box::new(Fetch::default())]))
+}
+
+thread_local! {
+    static LOADER: RefCell<Rc<dyn SourceLoader>> = RefCell::new(default());
+}
+
+pub(crate) fn current() -> Rc<dyn SourceLoader> {
+    LOADER.with(|loader| loader.borrow().clone())
+}
+
+/// Replaces the loader for modules not fetched yet.
+pub fn set(loader: impl SourceLoader + 'static) {
+    LOADER.with(|current| *current.borrow_mut() = Rc::new(loader));
+}
+
+/// Loads sources through `callback` after the VFS; without one, `fetch` is restored.
+#[wasm_bindgen(js_name = setLoader)]
+pub fn set_loader(callback: Option<LoaderCallback>) {
+    match callback {
+        Some(callback) => set(Chain(vec![Box::new(Vfs), Box::new(Callback(callback.unchecked_into()))])),
+        None => LOADER.with(|current| *current.borrow_mut() = default()),
+    }
+}
@@ -0,0 +1,70 @@
+//! CommonJS output, for Node's `require`. swc's pass leaves `import.meta` in place, which is a
+//! syntax error outside ES modules, so it is first rewritten to what Node has for a CommonJS file:
+//! `import.meta.url` to the file URL of `__filename`, `import.meta.filename` and `.dirname` to
+//! `__filename` and `__dirname`, and any other use to an object with those three.
+
+use swc_common::{DUMMY_SP, Mark};
+use swc_ecma_ast::{CallExpr, Expr, ExprOrSuper, KeyValueProp, Lit, MemberExpr, MetaPropExpr, Module, ObjectLit, Prop, PropName, PropOrSpread};
+use swc_ecma_utils::{ExprFactory, quote_ident, quote_str};
+use swc_ecma_visit::{Fold, FoldWith, noop_fold_type};
+
+fn is_import_meta(expr: &Expr) -> bool {
+    matches!(expr, Expr::MetaProp(MetaPropExpr { meta, prop }) if &*meta.sym == "import" && &*prop.sym == "meta")
+}
+
+/// `require("url").pathToFileURL(__filename).href`
+fn url() -> Expr {
+    let call = |callee: Expr, arg: Expr| Expr::Call(CallExpr { span: DUMMY_SP, callee: callee.as_callee(), args: vec![arg.as_arg()], type_args: None });
+    let url = call(Expr::Ident(quote_ident!("require")), Expr::Lit(Lit::Str(quote_str!("url"))));
+    let file_url = call(url.make_member(quote_ident!("pathToFileURL")), Expr::Ident(quote_ident!("__filename")));
+    file_url.make_member(quote_ident!("href"))
+}
+
+fn meta(prop: &str) -> Option<Expr> {
+    match prop {
+        "url" => Some(url()),
+        "filename" => Some(Expr::Ident(quote_ident!("__filename"))),
+        "dirname" => Some(Expr::Ident(quote_ident!("__dirname"))),
+        _ => None,
+    }
+}
+
+struct ImportMeta;
+impl Fold for ImportMeta {
+    noop_fold_type!();
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        if let Expr::Member(MemberExpr { obj: ExprOrSuper::Expr(obj), prop, computed: false, .. }) = &expr {
+            if let (true, Expr::Ident(prop)) = (is_import_meta(obj), &**prop) {
+                if let Some(value) = meta(&prop.sym) {
+                    return value;
+                }
+            }
+        }
+        match expr {
+            expr if is_import_meta(&expr) => Expr::Object(ObjectLit {
+                span: DUMMY_SP,
+                props: ["url", "filename", "dirname"].iter().filter_map(|name| Some(PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+                    key: PropName::Ident(quote_ident!(*name)),
+                    value: Box::new(meta(name)?),
+                }))))).collect(),
+            }),
+            _ => expr.fold_children_with(self),
+        }
+    }
+}
+
+struct CommonJs;
+impl Fold for CommonJs {
+    noop_fold_type!();
+
+    fn fold_module(&mut self, module: Module) -> Module {
+        module
+            .fold_with(&mut ImportMeta)
+            .fold_with(&mut swc_ecma_transforms_module::common_js::common_js(Mark::fresh(Mark::root()), Default::default()))
+    }
+}
+
+pub fn common_js() -> impl Fold {
+    CommonJs
+}
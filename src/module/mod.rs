@@ -0,0 +1,36 @@
+//! Module formats beyond the AMD and ES output `compile` gets straight from swc.
+
+pub mod common_js;
+pub mod system;
+pub mod umd;
+
+use swc_common::BytePos;
+use swc_ecma_parser::{JscTarget, StringInput, Syntax, lexer::Lexer, token::{Token, Word}};
+
+use crate::ModuleFormat;
+
+/// The byte offset just past the `(` of the `define(` or `System.register(` call that registers
+/// compiled `code`, where the runtime takes a module key as first argument. The code is lexed, so
+/// comments and strings mentioning either call are skipped. CommonJS and ES output have no such call.
+pub fn registration(code: &str, format: ModuleFormat) -> Option<usize> {
+    let callee: &[&str] = match format {
+        ModuleFormat::Amd | ModuleFormat::Umd => &["define", "("],
+        ModuleFormat::System => &["System", ".", "register", "("],
+        ModuleFormat::CommonJs | ModuleFormat::Es => return None,
+    };
+    let lexer = Lexer::new(Syntax::Es(Default::default()), JscTarget::Es2020, StringInput::new(code, BytePos(0), BytePos(code.len() as u32)), None);
+    let mut matched = 0;
+    for token in lexer {
+        let text = match &token.token {
+            Token::Word(Word::Ident(sym)) => &**sym,
+            Token::Dot => ".",
+            Token::LParen => "(",
+            _ => "",
+        };
+        matched = if text == callee[matched] { matched + 1 } else { (text == callee[0]) as usize };
+        if matched == callee.len() {
+            return Some(token.span.hi.0 as usize);
+        }
+    }
+    None
+}
@@ -0,0 +1,372 @@
+//! `System.register` output. Unlike AMD it keeps ES semantics: imports are plain variables that
+//! each dependency's setter reassigns whenever the dependency exports, every assignment to an
+//! exported binding is reported through `_export`, and a module with top-level `await` gets an
+//! async `execute`. Top-level declarations are hoisted out of `execute` so the hoisted functions
+//! and the setters can see them.
+
+use fxhash::FxHashMap;
+use swc_common::{DUMMY_SP, Mark};
+use swc_ecma_ast::{
+    ArrayLit, ArrowExpr, AssignExpr, AwaitExpr, BinExpr, BlockStmt, CallExpr, ClassDecl, ClassExpr, Decl, DefaultDecl,
+    ExportDecl, ExportDefaultDecl, Expr, ExprOrSuper, FnDecl, FnExpr, ForInStmt, ForOfStmt, Function, Ident,
+    IfStmt, ImportSpecifier, Invalid, KeyValueProp, Lit, MemberExpr, MetaPropExpr, Module, ModuleDecl, ModuleItem, ExportSpecifier,
+    Number, ObjectLit, Param, Pat, PatOrExpr, Prop, PropName, PropOrSpread, ReturnStmt, SeqExpr, Stmt, VarDecl,
+    VarDeclKind, VarDeclOrPat, VarDeclarator, op,
+};
+use swc_ecma_transforms_base::{fixer::fixer, resolver::resolver_with_mark};
+use swc_ecma_utils::{DestructuringFinder, ExprFactory, Id, ident::IdentLike, private_ident, quote_ident, quote_str};
+use swc_ecma_visit::{Fold, FoldWith, Node, Visit, VisitWith, noop_fold_type, noop_visit_type};
+
+fn bindings<N: for<'a> VisitWith<DestructuringFinder<'a, Ident>>>(node: &N) -> Vec<Ident> {
+    let mut found = vec![];
+    node.visit_with(&Invalid { span: DUMMY_SP } as _, &mut DestructuringFinder { found: &mut found });
+    found
+}
+
+fn str(value: &str) -> Expr {
+    Expr::Lit(Lit::Str(quote_str!(value)))
+}
+
+fn computed(obj: &Ident, prop: Expr) -> Expr {
+    Expr::Member(MemberExpr { span: DUMMY_SP, obj: obj.clone().as_obj(), prop: Box::new(prop), computed: true })
+}
+
+fn assign(left: Pat, right: Expr) -> Stmt {
+    AssignExpr { span: DUMMY_SP, op: op!("="), left: PatOrExpr::Pat(Box::new(left)), right: Box::new(right) }.into_stmt()
+}
+
+fn function(params: Vec<Ident>, stmts: Vec<Stmt>, is_async: bool) -> Expr {
+    Expr::Fn(FnExpr {
+        ident: None,
+        function: Function {
+            params: params.into_iter().map(|p| Param { span: DUMMY_SP, decorators: vec![], pat: Pat::Ident(p.into()) }).collect(),
+            decorators: vec![],
+            span: DUMMY_SP,
+            body: Some(BlockStmt { span: DUMMY_SP, stmts }),
+            is_generator: false,
+            is_async,
+            type_params: None,
+            return_type: None,
+        },
+    })
+}
+
+#[derive(Default)]
+struct TopLevelAwait(bool);
+impl Visit for TopLevelAwait {
+    noop_visit_type!();
+
+    fn visit_function(&mut self, _: &Function, _: &dyn Node) {}
+    fn visit_arrow_expr(&mut self, _: &ArrowExpr, _: &dyn Node) {}
+    fn visit_await_expr(&mut self, _: &AwaitExpr, _: &dyn Node) { self.0 = true; }
+    fn visit_for_of_stmt(&mut self, s: &ForOfStmt, _: &dyn Node) {
+        self.0 |= s.await_token.is_some();
+        s.visit_children_with(self);
+    }
+}
+
+struct System {
+    top_level_mark: Mark,
+    export: Ident,
+    context: Ident,
+    /// The setters' parameter, a dependency's exports.
+    module: Ident,
+    /// Exported names of each top-level binding.
+    exports: FxHashMap<Id, Vec<String>>,
+}
+
+#[derive(Default)]
+struct Hoisted {
+    deps: Vec<(String, Vec<Stmt>)>,
+    vars: Vec<Ident>,
+    functions: Vec<Stmt>,
+    body: Vec<Stmt>,
+}
+
+impl Hoisted {
+    fn setter(&mut self, src: &str) -> &mut Vec<Stmt> {
+        let index = match self.deps.iter().position(|(dep, _)| dep == src) {
+            Some(index) => index,
+            None => { self.deps.push((src.to_string(), vec![])); self.deps.len() - 1 }
+        };
+        &mut self.deps[index].1
+    }
+
+    fn decl(&mut self, decl: Decl) {
+        match decl {
+            Decl::Fn(f) => self.functions.push(Stmt::Decl(Decl::Fn(f))),
+            Decl::Class(ClassDecl { ident, class, .. }) => {
+                self.vars.push(ident.clone());
+                self.body.push(assign(Pat::Ident(ident.clone().into()), Expr::Class(ClassExpr { ident: Some(ident), class })));
+            }
+            Decl::Var(var) => for d in var.decls {
+                self.vars.extend(bindings(&d.name));
+                if let Some(init) = d.init {
+                    self.body.push(assign(d.name, *init));
+                }
+            },
+            decl => self.body.push(Stmt::Decl(decl)),
+        }
+    }
+}
+
+impl System {
+    fn export_call(&self, name: &str, value: Expr) -> Expr {
+        CallExpr {
+            span: DUMMY_SP,
+            callee: self.export.clone().as_callee(),
+            args: vec![str(name).as_arg(), value.as_arg()],
+            type_args: None,
+        }.into()
+    }
+
+    fn exported(&self, idents: &[Ident]) -> Vec<(String, Ident)> {
+        idents.iter().flat_map(|ident| {
+            self.exports.get(&ident.to_id()).into_iter().flatten().map(move |name| (name.clone(), ident.clone()))
+        }).collect()
+    }
+
+    fn add_export(&mut self, ident: &Ident, name: &str) {
+        self.exports.entry(ident.to_id()).or_default().push(name.to_string());
+    }
+
+    fn collect(&mut self, module: &Module) {
+        for item in &module.body {
+            match item {
+                ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { decl, .. })) => match decl {
+                    Decl::Fn(f) => self.add_export(&f.ident, &f.ident.sym),
+                    Decl::Class(c) => self.add_export(&c.ident, &c.ident.sym),
+                    Decl::Var(var) => for ident in bindings(&var.decls) { self.add_export(&ident, &ident.sym) },
+                    _ => {}
+                },
+                ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(export)) if export.src.is_none() => for s in &export.specifiers {
+                    if let ExportSpecifier::Named(s) = s {
+                        self.add_export(&s.orig, &s.exported.as_ref().unwrap_or(&s.orig).sym);
+                    }
+                },
+                _ => {}
+            }
+        }
+    }
+
+    /// `for (var _k in _m) if (_k !== "default") _e[_k] = _m[_k]; _export(_e);`
+    fn export_all(&self, m: &Ident) -> Vec<Stmt> {
+        let (e, k) = (private_ident!("_e"), private_ident!("_k"));
+        let copy = assign(Pat::Expr(Box::new(computed(&e, Expr::Ident(k.clone())))), computed(m, Expr::Ident(k.clone())));
+        vec![
+            Stmt::Decl(Decl::Var(VarDecl {
+                span: DUMMY_SP,
+                kind: VarDeclKind::Var,
+                declare: false,
+                decls: vec![VarDeclarator { span: DUMMY_SP, name: Pat::Ident(e.clone().into()), init: Some(Box::new(Expr::Object(ObjectLit { span: DUMMY_SP, props: vec![] }))), definite: false }],
+            })),
+            Stmt::ForIn(ForInStmt {
+                span: DUMMY_SP,
+                left: VarDeclOrPat::VarDecl(VarDecl {
+                    span: DUMMY_SP,
+                    kind: VarDeclKind::Var,
+                    declare: false,
+                    decls: vec![VarDeclarator { span: DUMMY_SP, name: Pat::Ident(k.clone().into()), init: None, definite: false }],
+                }),
+                right: Box::new(Expr::Ident(m.clone())),
+                body: Box::new(Stmt::If(IfStmt {
+                    span: DUMMY_SP,
+                    test: Box::new(Expr::Bin(BinExpr { span: DUMMY_SP, op: op!("!=="), left: Box::new(Expr::Ident(k)), right: Box::new(str("default")) })),
+                    cons: Box::new(copy),
+                    alt: None,
+                })),
+            }),
+            CallExpr { span: DUMMY_SP, callee: self.export.clone().as_callee(), args: vec![e.as_arg()], type_args: None }.into_stmt(),
+        ]
+    }
+
+    fn hoist(&mut self, body: Vec<ModuleItem>) -> Hoisted {
+        let m = self.module.clone();
+        let mut hoisted = Hoisted::default();
+        for item in body {
+            let decl = match item {
+                ModuleItem::Stmt(Stmt::Decl(decl)) => { hoisted.decl(decl); continue; }
+                ModuleItem::Stmt(stmt) => { hoisted.body.push(stmt); continue; }
+                ModuleItem::ModuleDecl(decl) => decl,
+            };
+            match decl {
+                ModuleDecl::Import(import) => {
+                    for s in import.specifiers {
+                        let (local, value) = match s {
+                            ImportSpecifier::Named(s) => {
+                                let imported = s.imported.clone().unwrap_or_else(|| s.local.clone());
+                                (s.local, m.clone().make_member(quote_ident!(imported.sym)))
+                            }
+                            ImportSpecifier::Default(s) => (s.local, m.clone().make_member(quote_ident!("default"))),
+                            ImportSpecifier::Namespace(s) => (s.local, Expr::Ident(m.clone())),
+                        };
+                        hoisted.vars.push(local.clone());
+                        hoisted.setter(&import.src.value).push(assign(Pat::Ident(local.into()), value));
+                    }
+                    hoisted.setter(&import.src.value);
+                }
+                ModuleDecl::ExportNamed(export) => if let Some(src) = export.src {
+                    for s in export.specifiers {
+                        let (name, value) = match s {
+                            ExportSpecifier::Named(s) => {
+                                let name = s.exported.as_ref().unwrap_or(&s.orig).sym.to_string();
+                                (name, m.clone().make_member(quote_ident!(s.orig.sym)))
+                            }
+                            ExportSpecifier::Default(s) => (s.exported.sym.to_string(), m.clone().make_member(quote_ident!("default"))),
+                            ExportSpecifier::Namespace(s) => (s.name.sym.to_string(), Expr::Ident(m.clone())),
+                        };
+                        let call = self.export_call(&name, value).into_stmt();
+                        hoisted.setter(&src.value).push(call);
+                    }
+                },
+                ModuleDecl::ExportAll(export) => {
+                    let stmts = self.export_all(&m);
+                    hoisted.setter(&export.src.value).extend(stmts);
+                }
+                ModuleDecl::ExportDecl(ExportDecl { decl, .. }) => hoisted.decl(decl),
+                ModuleDecl::ExportDefaultDecl(ExportDefaultDecl { decl, .. }) => match decl {
+                    DefaultDecl::Fn(FnExpr { ident, function }) => {
+                        let ident = ident.unwrap_or_else(|| private_ident!("_default"));
+                        self.add_export(&ident, "default");
+                        hoisted.functions.push(Stmt::Decl(Decl::Fn(FnDecl { ident, declare: false, function })));
+                    }
+                    DefaultDecl::Class(ClassExpr { ident, class }) => {
+                        let local = ident.clone().unwrap_or_else(|| private_ident!("_default"));
+                        self.add_export(&local, "default");
+                        hoisted.vars.push(local.clone());
+                        hoisted.body.push(assign(Pat::Ident(local.into()), Expr::Class(ClassExpr { ident, class })));
+                    }
+                    DefaultDecl::TsInterfaceDecl(_) => {}
+                },
+                ModuleDecl::ExportDefaultExpr(export) => hoisted.body.push(self.export_call("default", *export.expr).into_stmt()),
+                _ => {}
+            }
+        }
+        hoisted
+    }
+}
+
+impl Fold for System {
+    noop_fold_type!();
+
+    fn fold_module(&mut self, module: Module) -> Module {
+        let module = module.fold_with(&mut resolver_with_mark(self.top_level_mark));
+        self.collect(&module);
+        let Hoisted { deps, vars, functions, body } = self.hoist(module.body);
+
+        let mut tla = TopLevelAwait::default();
+        body.visit_with(&Invalid { span: DUMMY_SP } as _, &mut tla);
+
+        let mut stmts = vec![str("use strict").into_stmt()];
+        if !vars.is_empty() {
+            stmts.push(Stmt::Decl(Decl::Var(VarDecl {
+                span: DUMMY_SP,
+                kind: VarDeclKind::Var,
+                declare: false,
+                decls: vars.into_iter().map(|v| VarDeclarator { span: DUMMY_SP, name: Pat::Ident(v.into()), init: None, definite: false }).collect(),
+            })));
+        }
+        // Functions are hoisted, so their exports exist before any dependency runs.
+        let initial: Vec<Stmt> = functions.iter().filter_map(|f| match f {
+            Stmt::Decl(Decl::Fn(f)) => Some(f.ident.clone()),
+            _ => None,
+        }).flat_map(|ident| self.exported(&[ident])).map(|(name, ident)| self.export_call(&name, Expr::Ident(ident)).into_stmt()).collect();
+        stmts.extend(functions);
+        stmts.extend(initial);
+
+        let setters = deps.iter().map(|(_, setter)| Some(function(vec![self.module.clone()], setter.clone(), false).as_arg())).collect();
+        let declaration = ObjectLit {
+            span: DUMMY_SP,
+            props: vec![
+                PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+                    key: PropName::Ident(quote_ident!("setters")),
+                    value: Box::new(Expr::Array(ArrayLit { span: DUMMY_SP, elems: setters })),
+                }))),
+                PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+                    key: PropName::Ident(quote_ident!("execute")),
+                    value: Box::new(function(vec![], body, tla.0)),
+                }))),
+            ],
+        };
+        stmts.push(Stmt::Return(ReturnStmt { span: DUMMY_SP, arg: Some(Box::new(Expr::Object(declaration))) }));
+        let stmts = stmts.fold_with(self);
+
+        let register = CallExpr {
+            span: DUMMY_SP,
+            callee: quote_ident!("System").make_member(quote_ident!("register")).as_callee(),
+            args: vec![
+                Expr::Array(ArrayLit { span: DUMMY_SP, elems: deps.iter().map(|(src, _)| Some(str(src).as_arg())).collect() }).as_arg(),
+                function(vec![self.export.clone(), self.context.clone()], stmts, false).as_arg(),
+            ],
+            type_args: None,
+        };
+        Module { body: vec![ModuleItem::Stmt(register.into_stmt())], ..module }.fold_with(&mut fixer(None))
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        match expr.fold_children_with(self) {
+            Expr::Assign(assign) => {
+                let (exported, simple) = match &assign.left {
+                    PatOrExpr::Pat(pat) => (self.exported(&bindings(&**pat)), matches!(&**pat, Pat::Ident(_))),
+                    PatOrExpr::Expr(expr) => match &**expr {
+                        Expr::Ident(ident) => (self.exported(std::slice::from_ref(ident)), true),
+                        _ => (vec![], true),
+                    },
+                };
+                if simple {
+                    exported.iter().fold(Expr::Assign(assign), |value, (name, _)| self.export_call(name, value))
+                } else if exported.is_empty() {
+                    Expr::Assign(assign)
+                } else {
+                    let mut exprs = vec![Box::new(Expr::Assign(assign))];
+                    exprs.extend(exported.into_iter().map(|(name, ident)| Box::new(self.export_call(&name, Expr::Ident(ident)))));
+                    Expr::Seq(SeqExpr { span: DUMMY_SP, exprs })
+                }
+            }
+            Expr::Update(update) => {
+                let exported = match &*update.arg {
+                    Expr::Ident(ident) => self.exported(std::slice::from_ref(ident)),
+                    _ => vec![],
+                };
+                if exported.is_empty() {
+                    Expr::Update(update)
+                } else if update.prefix {
+                    exported.iter().fold(Expr::Update(update), |value, (name, _)| self.export_call(name, value))
+                } else {
+                    // `x++` reports `x + 1` and still evaluates to the old value.
+                    let step = if update.op == op!("++") { op!(bin, "+") } else { op!(bin, "-") };
+                    let mut exprs: Vec<Box<Expr>> = exported.into_iter().map(|(name, ident)| Box::new(self.export_call(&name, Expr::Bin(BinExpr {
+                        span: DUMMY_SP,
+                        op: step,
+                        left: Box::new(Expr::Ident(ident)),
+                        right: Box::new(Expr::Lit(Lit::Num(Number { span: DUMMY_SP, value: 1.0 }))),
+                    })))).collect();
+                    exprs.push(Box::new(Expr::Update(update)));
+                    Expr::Seq(SeqExpr { span: DUMMY_SP, exprs })
+                }
+            }
+            Expr::MetaProp(MetaPropExpr { meta, prop }) if &*meta.sym == "import" && &*prop.sym == "meta" =>
+                self.context.clone().make_member(quote_ident!("meta")),
+            Expr::Call(CallExpr { span, callee, args, type_args }) => {
+                let callee = match callee {
+                    ExprOrSuper::Expr(expr) if matches!(&*expr, Expr::Ident(i) if &*i.sym == "import") =>
+                        self.context.clone().make_member(quote_ident!("import")).as_callee(),
+                    callee => callee,
+                };
+                Expr::Call(CallExpr { span, callee, args, type_args })
+            }
+            expr => expr,
+        }
+    }
+}
+
+/// Wraps a module in `System.register(deps, function (_export, _context) { ... })`.
+pub fn system() -> impl Fold {
+    System {
+        top_level_mark: Mark::fresh(Mark::root()),
+        export: private_ident!("_export"),
+        context: private_ident!("_context"),
+        module: private_ident!("_m"),
+        exports: Default::default(),
+    }
+}
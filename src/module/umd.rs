@@ -0,0 +1,161 @@
+//! UMD output. The module is first compiled to the AMD `define([deps], factory)` the twasm runtime
+//! loads, then that call is wrapped so the same factory also runs elsewhere: the wrapper registers
+//! through `define` when an AMD loader is present (the twasm runtime sets `define.amd`, so UMD
+//! modules load in-page like AMD ones), calls `require` under CommonJS, and otherwise reads its
+//! imports from and assigns its exports to globals, for plain `<script>` tags.
+
+use std::collections::BTreeMap;
+use swc_common::{DUMMY_SP, FileName, SourceMap, Span, sync::Lrc};
+use swc_ecma_ast::{Expr, ExprOrSpread, ExprOrSuper, ExprStmt, Invalid, Lit, Module, ModuleItem, Stmt};
+use swc_ecma_parser::{Parser, StringInput, Syntax};
+use swc_ecma_visit::{Fold, FoldWith, noop_fold_type};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    /// Global the exports are assigned to without a module loader. Defaults to the camel-cased
+    /// file name.
+    pub name: Option<String>,
+    /// Global standing in for each import specifier without a module loader, e.g. `react` to
+    /// `React`. Unmapped specifiers use their camel-cased file name.
+    pub globals: BTreeMap<String, String>,
+}
+
+/// `react-dom/server.js` as `server`, `lodash.debounce` as `lodashDebounce`.
+fn camel_case(spec: &str) -> String {
+    let name = spec.rsplit('/').next().unwrap_or(spec);
+    let name = [".d.ts", ".ts", ".tsx", ".js", ".jsx", ".mjs", ".cjs"].iter()
+        .find_map(|ext| name.strip_suffix(ext)).unwrap_or(name);
+    let mut words = name.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty());
+    let mut out = words.next().unwrap_or("_").to_string();
+    for word in words {
+        let mut chars = word.chars();
+        out.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+        out.push_str(chars.as_str());
+    }
+    if out.starts_with(|c: char| c.is_ascii_digit()) {
+        out.insert(0, '_');
+    }
+    out
+}
+
+/// `global.name`, or `global["name"]` when `name` is not an identifier.
+fn global(name: &str) -> String {
+    let ident = name.starts_with(|c: char| c.is_alphabetic() || c == '_' || c == '$')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+    match ident {
+        true => format!("global.{}", name),
+        false => format!("global[{}]", serde_json::Value::from(name)),
+    }
+}
+
+/// The dependency list and factory of a top-level `define([deps], factory)`.
+fn define_call(item: &mut ModuleItem) -> Option<(Vec<String>, Box<Expr>)> {
+    let call = match item {
+        ModuleItem::Stmt(Stmt::Expr(ExprStmt { expr, .. })) => match &mut **expr {
+            Expr::Call(call) => call,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    match &call.callee {
+        ExprOrSuper::Expr(callee) => match &**callee {
+            Expr::Ident(ident) if &*ident.sym == "define" && call.args.len() == 2 => {}
+            _ => return None,
+        },
+        _ => return None,
+    }
+    let deps = match &*call.args[0].expr {
+        Expr::Array(array) => array.elems.iter().map(|elem| match elem {
+            Some(ExprOrSpread { expr, spread: None }) => match &**expr {
+                Expr::Lit(Lit::Str(s)) => Some(s.value.to_string()),
+                _ => None,
+            },
+            _ => None,
+        }).collect::<Option<Vec<_>>>()?,
+        _ => return None,
+    };
+    let factory = std::mem::replace(&mut call.args[1].expr, Box::new(Expr::Invalid(Invalid { span: DUMMY_SP })));
+    Some((deps, factory))
+}
+
+/// Puts the factory in place of the template's placeholder and drops the template's spans, which
+/// point into a source file that isn't the module's.
+struct Splice(Option<Box<Expr>>);
+impl Fold for Splice {
+    noop_fold_type!();
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        match expr {
+            Expr::Ident(ident) if &*ident.sym == "__twasm_factory" => self.0.take().map_or(Expr::Ident(ident), |factory| *factory),
+            _ => expr.fold_children_with(self),
+        }
+    }
+
+    fn fold_span(&mut self, _: Span) -> Span {
+        DUMMY_SP
+    }
+}
+
+struct Umd {
+    cm: Lrc<SourceMap>,
+    name: String,
+    globals: BTreeMap<String, String>,
+}
+
+impl Umd {
+    fn wrap(&self, deps: &[String], factory: Box<Expr>) -> Vec<ModuleItem> {
+        let args = |cjs: bool| deps.iter().map(|dep| match (dep.as_str(), cjs) {
+            ("exports", true) => "exports".to_string(),
+            ("exports", false) => "mod.exports".to_string(),
+            ("module", true) => "module".to_string(),
+            ("module", false) => "mod".to_string(),
+            ("require", true) => "require".to_string(),
+            ("require", false) => "undefined".to_string(),
+            (spec, true) => format!("require({})", serde_json::Value::from(spec)),
+            (spec, false) => global(self.globals.get(spec).cloned().unwrap_or_else(|| camel_case(spec)).as_str()),
+        }).collect::<Vec<_>>().join(", ");
+        let source = format!(
+            r#"(function (global, factory) {{
+                if (typeof define === "function" && define.amd) define({deps}, factory);
+                else if (typeof exports === "object" && typeof module !== "undefined") factory({cjs});
+                else {{
+                    var mod = {{ exports: {{}} }};
+                    factory({globals});
+                    {name} = mod.exports;
+                }}
+            }})(typeof globalThis !== "undefined" ? globalThis : this, __twasm_factory);"#,
+            deps = serde_json::Value::from(deps.to_vec()),
+            cjs = args(true),
+            globals = args(false),
+            name = global(&self.name),
+        );
+        let fm = self.cm.new_source_file(FileName::Anon, source);
+        let template = Parser::new(Syntax::default(), StringInput::from(&*fm), None).parse_module()
+            .expect("the UMD wrapper is valid JS");
+        template.fold_with(&mut Splice(Some(factory))).body
+    }
+}
+
+impl Fold for Umd {
+    noop_fold_type!();
+
+    fn fold_module(&mut self, module: Module) -> Module {
+        let mut module = module.fold_with(&mut swc_ecma_transforms_module::amd::amd(Default::default()));
+        let mut body = Vec::with_capacity(module.body.len());
+        for mut item in module.body.drain(..) {
+            match define_call(&mut item) {
+                Some((deps, factory)) => body.extend(self.wrap(&deps, factory)),
+                None => body.push(item),
+            }
+        }
+        Module { body, ..module }
+    }
+}
+
+pub fn umd(cm: Lrc<SourceMap>, filename: &str, config: &Config) -> impl Fold {
+    Umd {
+        cm,
+        name: config.name.clone().unwrap_or_else(|| camel_case(filename)),
+        globals: config.globals.clone(),
+    }
+}
@@ -0,0 +1,215 @@
+//! Compile options. The browser sets them once through `configure`; native callers such as the
+//! bundler pass an `Options` directly.
+
+use std::{cell::RefCell, collections::BTreeMap};
+use js_sys::{Array, JSON, Object, Reflect};
+use wasm_bindgen::{JsCast, prelude::*};
+
+use crate::{api::OptionsObject, module::umd};
+
+/// Which source comments survive into the emitted code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CommentMode {
+    None,
+    All,
+    /// License headers and `@__PURE__` annotations only.
+    #[default]
+    Legal,
+}
+
+impl CommentMode {
+    pub fn from_name(name: &str) -> Option<CommentMode> {
+        match name {
+            "none" => Some(CommentMode::None),
+            "all" => Some(CommentMode::All),
+            "legal" => Some(CommentMode::Legal),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CommentMode::None => "none",
+            CommentMode::All => "all",
+            CommentMode::Legal => "legal",
+        }
+    }
+}
+
+/// Module format of the emitted code. `Amd` is what the in-page runtime evaluates, `Umd` runs there
+/// too as well as under other AMD loaders, CommonJS and plain `<script>` tags, and `System` runs
+/// there or under SystemJS with ES live bindings, cycles and top-level `await`; `CommonJs` and `Es`
+/// are for Node's `require` hook and ESM loader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ModuleFormat {
+    #[default]
+    Amd,
+    Umd,
+    System,
+    CommonJs,
+    Es,
+}
+
+impl ModuleFormat {
+    pub fn from_name(name: &str) -> Option<ModuleFormat> {
+        match name {
+            "amd" => Some(ModuleFormat::Amd),
+            "umd" => Some(ModuleFormat::Umd),
+            "system" => Some(ModuleFormat::System),
+            "commonjs" => Some(ModuleFormat::CommonJs),
+            "es" => Some(ModuleFormat::Es),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ModuleFormat::Amd => "amd",
+            ModuleFormat::Umd => "umd",
+            ModuleFormat::System => "system",
+            ModuleFormat::CommonJs => "commonjs",
+            ModuleFormat::Es => "es",
+        }
+    }
+}
+
+/// How the in-page runtime runs compiled code. All of them append `//# sourceURL=<path>` so stack
+/// traces and devtools name the module; errors thrown while evaluating are attributed to it.
+///
+/// Under a strict CSP without `'unsafe-eval'`, `Function` and `Eval` are blocked. `Script` works
+/// with a `nonce`, and `Blob` is the fallback when no nonce is available: it only needs `blob:` in
+/// `script-src`, or `'strict-dynamic'`. With `require-trusted-types-for 'script'`, set
+/// `trusted_types` to a policy name the CSP's `trusted-types` directive allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Evaluator {
+    /// `new Function(code)()`. Works in pages and workers.
+    #[default]
+    Function,
+    /// Indirect `eval`, so top-level declarations land in the global scope.
+    Eval,
+    /// A `<script>` pointing at a blob URL, removed once loaded; `importScripts` in classic workers.
+    Blob,
+    /// An inline `<script>` carrying the page's nonce, removed once run. Workers have no DOM and use
+    /// `Function` instead.
+    Script,
+}
+
+impl Evaluator {
+    pub fn from_name(name: &str) -> Option<Evaluator> {
+        match name {
+            "function" => Some(Evaluator::Function),
+            "eval" => Some(Evaluator::Eval),
+            "blob" => Some(Evaluator::Blob),
+            "script" => Some(Evaluator::Script),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Evaluator::Function => "function",
+            Evaluator::Eval => "eval",
+            Evaluator::Blob => "blob",
+            Evaluator::Script => "script",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// Runs the swc minifier (compress + mangle) and emits compact code.
+    pub minify: bool,
+    pub comments: CommentMode,
+    pub module: ModuleFormat,
+    pub umd: umd::Config,
+    pub evaluator: Evaluator,
+    /// CSP nonce for injected scripts. Defaults to the nonce of the first `<script nonce>` on the
+    /// page.
+    pub nonce: Option<String>,
+    /// Trusted Types policy that code and script URLs go through before reaching a sink.
+    pub trusted_types: Option<String>,
+    /// Shows compile and runtime errors in an overlay on the page, for development.
+    pub overlay: bool,
+    /// Compile-time constants by global name or dotted path, each JSON or a dotted identifier.
+    pub define: BTreeMap<String, String>,
+}
+
+impl Options {
+    fn from_js(value: &JsValue) -> Options {
+        let get = |name: &str| Reflect::get(value, &name.into()).ok();
+        // `define` values that aren't strings are taken as their JSON.
+        let entries = |name: &str, json: bool| get(name).filter(|v| v.is_object()).map(|v| Object::entries(v.unchecked_ref()).iter().filter_map(|entry| {
+            let entry: Array = entry.into();
+            let value = entry.get(1);
+            let value = match value.as_string() {
+                None if json => JSON::stringify(&value).ok().map(String::from),
+                value => value,
+            };
+            Some((entry.get(0).as_string()?, value?))
+        }).collect()).unwrap_or_default();
+        Options {
+            minify: get("minify").and_then(|v| v.as_bool()).unwrap_or_default(),
+            comments: get("comments").and_then(|v| v.as_string()).and_then(|v| CommentMode::from_name(&v)).unwrap_or_default(),
+            module: get("module").and_then(|v| v.as_string()).and_then(|v| ModuleFormat::from_name(&v)).unwrap_or_default(),
+            umd: umd::Config {
+                name: get("umdName").and_then(|v| v.as_string()),
+                globals: entries("globals", false),
+            },
+            evaluator: get("evaluator").and_then(|v| v.as_string()).and_then(|v| Evaluator::from_name(&v)).unwrap_or_default(),
+            nonce: get("nonce").and_then(|v| v.as_string()),
+            trusted_types: get("trustedTypes").and_then(|v| v.as_string()),
+            overlay: get("overlay").and_then(|v| v.as_bool()).unwrap_or_default(),
+            define: entries("define", true),
+        }
+    }
+
+    /// The inverse of `configure`'s parsing, used to hand the page's options to compiler workers.
+    pub(crate) fn to_js(&self) -> Result<JsValue, JsValue> {
+        let obj = Object::new();
+        Reflect::set(&obj, &"minify".into(), &self.minify.into())?;
+        Reflect::set(&obj, &"comments".into(), &self.comments.name().into())?;
+        Reflect::set(&obj, &"module".into(), &self.module.name().into())?;
+        if let Some(name) = &self.umd.name {
+            Reflect::set(&obj, &"umdName".into(), &name.into())?;
+        }
+        Reflect::set(&obj, &"globals".into(), &object(&self.umd.globals)?)?;
+        Reflect::set(&obj, &"evaluator".into(), &self.evaluator.name().into())?;
+        if let Some(nonce) = &self.nonce {
+            Reflect::set(&obj, &"nonce".into(), &nonce.into())?;
+        }
+        if let Some(policy) = &self.trusted_types {
+            Reflect::set(&obj, &"trustedTypes".into(), &policy.into())?;
+        }
+        Reflect::set(&obj, &"overlay".into(), &self.overlay.into())?;
+        Reflect::set(&obj, &"define".into(), &object(&self.define)?)?;
+        Ok(obj.into())
+    }
+}
+
+fn object(map: &BTreeMap<String, String>) -> Result<JsValue, JsValue> {
+    let obj = Object::new();
+    for (key, value) in map {
+        Reflect::set(&obj, &key.into(), &value.into())?;
+    }
+    Ok(obj.into())
+}
+
+thread_local! {
+    static OPTIONS: RefCell<Options> = RefCell::new(Options::default());
+}
+
+pub(crate) fn current() -> Options {
+    OPTIONS.with(|o| o.borrow().clone())
+}
+
+#[wasm_bindgen]
+pub fn configure(options: &OptionsObject) {
+    OPTIONS.with(|o| *o.borrow_mut() = Options::from_js(options));
+    crate::scripts::begin();
+}
+
+/// The options last passed to `configure`, for changing one of them.
+#[wasm_bindgen(js_name = options)]
+pub fn configured() -> Result<OptionsObject, JsValue> {
+    Ok(current().to_js()?.unchecked_into())
+}
@@ -0,0 +1,89 @@
+//! Development error overlay. Compile diagnostics and mapped runtime errors are rendered over the
+//! page with a code frame and a link to the file; a successful hot update or a reload clears it.
+//! Everything is built with DOM and CSSOM calls, so it works under Trusted Types and a CSP
+//! without `'unsafe-inline'` styles.
+
+use wasm_bindgen::{JsCast, prelude::*};
+use web_sys::{Document, HtmlElement};
+
+use crate::{Error, Result, options, report::{Diagnostic, Location}};
+
+const ID: &str = "__twasm_overlay";
+const ROOT: &str = "position:fixed;inset:0;z-index:2147483647;overflow:auto;padding:24px;background:rgba(24,24,27,.95);color:#e4e4e7;font:14px/1.5 ui-monospace,monospace";
+const HEADER: &str = "display:flex;justify-content:space-between;color:#f87171;font-size:18px;margin-bottom:16px";
+const CLOSE: &str = "background:none;border:0;color:inherit;font:inherit;cursor:pointer";
+const MESSAGE: &str = "margin-top:16px;white-space:pre-wrap";
+const LINK: &str = "color:#60a5fa";
+const FRAME: &str = "margin:8px 0 0;padding:8px;background:#27272a;overflow:auto";
+
+fn element(document: &Document, tag: &str, css: &str, text: Option<&str>) -> Result<HtmlElement> {
+    let elem: HtmlElement = document.create_element(tag)?.unchecked_into();
+    elem.style().set_css_text(css);
+    elem.set_text_content(text);
+    Ok(elem)
+}
+
+/// The source line with its number and a caret under the column.
+fn frame(location: &Location) -> Option<String> {
+    let snippet = location.snippet.as_ref()?;
+    let gutter = format!("{} | ", location.line);
+    Some(format!("{}{}\n{}^", gutter, snippet, " ".repeat(gutter.len() + location.column.saturating_sub(1) as usize)))
+}
+
+/// Replaces the overlay with `diagnostics`, when the `overlay` option is on and there is a page.
+pub(crate) fn show(title: &str, diagnostics: &[Diagnostic]) -> Result<()> {
+    if !options::current().overlay {
+        return Ok(());
+    }
+    let document = web_sys::window().ok_or(Error::InvalidWindow)?.document().ok_or(Error::InvalidDocument)?;
+    hide();
+
+    let root = element(&document, "div", ROOT, None)?;
+    root.set_id(ID);
+    let header = element(&document, "div", HEADER, Some(title))?;
+    let close = element(&document, "button", CLOSE, Some("×"))?;
+    close.set_onclick(Some(Closure::once_into_js(hide).unchecked_ref()));
+    header.append_child(&close)?;
+    root.append_child(&header)?;
+
+    for diagnostic in diagnostics {
+        let message = element(&document, "div", MESSAGE, Some(&diagnostic.message))?;
+        root.append_child(&message)?;
+        if let Some(location) = &diagnostic.location {
+            let link = element(&document, "a", LINK, Some(&format!("{}:{}:{}", location.file, location.line, location.column)))?;
+            link.set_attribute("href", &location.file)?;
+            link.set_attribute("target", "_blank")?;
+            root.append_child(&link)?;
+            if let Some(frame) = frame(location) {
+                let frame = element(&document, "pre", FRAME, Some(&frame))?;
+                root.append_child(&frame)?;
+            }
+        }
+    }
+    document.body().ok_or(Error::InvalidDocument)?.append_child(&root)?;
+    Ok(())
+}
+
+pub(crate) fn visible() -> bool {
+    web_sys::window().and_then(|w| w.document()).and_then(|d| d.get_element_by_id(ID)).is_some()
+}
+
+pub(crate) fn hide() {
+    if let Some(overlay) = web_sys::window().and_then(|w| w.document()).and_then(|d| d.get_element_by_id(ID)) {
+        overlay.remove();
+    }
+}
+
+/// Shows a failed compile, whether it ran here (`Diagnostics`) or in a pool worker, which rejects
+/// with the `{ message, diagnostics }` object `Error::Diagnostics` converts to.
+pub(crate) fn compile_error(error: &Error) {
+    let diagnostics = match error {
+        Error::Diagnostics(diagnostics) => diagnostics.clone(),
+        Error::JSError(e) => js_sys::Reflect::get(e, &"diagnostics".into()).ok()
+            .and_then(|d| js_sys::JSON::stringify(&d).ok())
+            .and_then(|json| serde_json::from_str(&String::from(json)).ok())
+            .unwrap_or_else(|| vec![Diagnostic { message: e.as_string().unwrap_or_else(|| format!("{:?}", e)), location: None }]),
+        e => vec![Diagnostic { message: format!("{:?}", e), location: None }],
+    };
+    show("Failed to compile", &diagnostics).ok();
+}
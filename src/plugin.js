@@ -0,0 +1,21 @@
+// Walks an swc AST parsed from JSON in place, children first, calling `visitor[node.type]` on each
+// node. A callback returns `undefined` to keep the node, a replacement node, or `null` to drop it
+// from the list it is in (or empty the field holding it).
+
+export function walk(node, visitor, filename) {
+    if (Array.isArray(node)) {
+        let kept = 0;
+        for (const item of node) {
+            const result = walk(item, visitor, filename);
+            if (result !== null) node[kept++] = result;
+        }
+        node.length = kept;
+        return node;
+    }
+    if (!node || typeof node !== 'object') return node;
+    for (const key of Object.keys(node)) node[key] = walk(node[key], visitor, filename);
+    const callback = typeof node.type === 'string' && visitor[node.type];
+    if (typeof callback !== 'function') return node;
+    const result = callback.call(visitor, node, filename);
+    return result === undefined ? node : result;
+}
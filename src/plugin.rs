@@ -0,0 +1,93 @@
+//! Project-specific transforms, run on every module after types are stripped and before the
+//! module format is applied, so they see plain ES with imports and exports intact.
+//!
+//! Rust code registers a factory that builds a `Fold` per file. JS registers a visitor keyed by
+//! swc node `type`, e.g. `{ CallExpression(node, filename) { ... } }`, called on the module
+//! serialized to JSON, children first. A visitor returns `undefined` to keep the node, a
+//! replacement node, or `null` to drop it from the list it is in. The module crosses into JS as
+//! one JSON string and comes back as one, so each JS visitor adds two serializations of the whole
+//! module per compile, however many nodes it matches; prefer a Rust fold for hot paths.
+//!
+//! Plugins are per thread, so modules compile on the page rather than the worker pool while any
+//! are registered. Their names are part of the compile cache key; rename a plugin when its output
+//! changes.
+
+use std::{cell::RefCell, rc::Rc};
+use js_sys::{JSON, Object};
+use swc_ecma_ast::Module;
+use swc_ecma_visit::{Fold, FoldWith};
+use wasm_bindgen::{JsCast, prelude::*};
+
+use crate::{Result, api::Visitor};
+
+#[wasm_bindgen(module = "/src/plugin.js")]
+extern "C" {
+    #[wasm_bindgen(catch)]
+    fn walk(node: JsValue, visitor: &Object, filename: &str) -> std::result::Result<JsValue, JsValue>;
+}
+
+type Factory = Box<dyn Fn(&str) -> Box<dyn Fold>>;
+
+enum Plugin {
+    Fold(Factory),
+    Visitor(Object),
+}
+
+thread_local! {
+    static PLUGINS: RefCell<Vec<(String, Rc<Plugin>)>> = Default::default();
+}
+
+fn insert(name: &str, plugin: Plugin) {
+    PLUGINS.with(|plugins| {
+        let mut plugins = plugins.borrow_mut();
+        let plugin = Rc::new(plugin);
+        match plugins.iter_mut().find(|(n, _)| n == name) {
+            Some(entry) => entry.1 = plugin,
+            None => plugins.push((name.to_string(), plugin)),
+        }
+    });
+}
+
+/// Registers `factory` under `name`, replacing a plugin of the same name. Plugins run in
+/// registration order.
+pub fn register(name: &str, factory: impl Fn(&str) -> Box<dyn Fold> + 'static) {
+    insert(name, Plugin::Fold(Box::new(factory)));
+}
+
+pub fn unregister(name: &str) {
+    PLUGINS.with(|plugins| plugins.borrow_mut().retain(|(n, _)| n != name));
+}
+
+pub(crate) fn names() -> Vec<String> {
+    PLUGINS.with(|plugins| plugins.borrow().iter().map(|(name, _)| name.clone()).collect())
+}
+
+fn visit(module: Module, visitor: &Object, filename: &str) -> Result<Module> {
+    let tree = walk(JSON::parse(&serde_json::to_string(&module)?)?, visitor, filename)?;
+    if tree.is_null() {
+        return Ok(module);
+    }
+    Ok(serde_json::from_str(&String::from(JSON::stringify(&tree)?))?)
+}
+
+pub(crate) fn apply(mut module: Module, filename: &str) -> Result<Module> {
+    let plugins: Vec<_> = PLUGINS.with(|plugins| plugins.borrow().iter().map(|(_, plugin)| plugin.clone()).collect());
+    for plugin in plugins {
+        module = match &*plugin {
+            Plugin::Fold(factory) => module.fold_with(&mut factory(filename)),
+            Plugin::Visitor(visitor) => visit(module, visitor, filename)?,
+        };
+    }
+    Ok(module)
+}
+
+/// Registers a JS visitor under `name`, replacing a plugin of the same name.
+#[wasm_bindgen(js_name = registerPlugin)]
+pub fn register_plugin(name: &str, visitor: Visitor) {
+    insert(name, Plugin::Visitor(visitor.unchecked_into()));
+}
+
+#[wasm_bindgen(js_name = unregisterPlugin)]
+pub fn unregister_plugin(name: &str) {
+    unregister(name);
+}
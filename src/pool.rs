@@ -0,0 +1,137 @@
+//! Pool of compiler workers, each running `www/worker.js` with its own copy of the wasm. Once a
+//! pool is started, `importModule` hands every cache miss to the next worker and evaluates the
+//! returned `{ code, map }` on the page, so independent files of a module graph compile in parallel.
+//! A worker that fails is dropped with its pending requests rejected; without any left, modules
+//! compile on the page again.
+
+use std::{cell::RefCell, rc::Rc};
+use fxhash::FxHashMap;
+use js_sys::{Function, Object, Promise, Reflect};
+use wasm_bindgen::{JsCast, prelude::*};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{MessageEvent, Worker, WorkerOptions, WorkerType};
+
+use crate::{Compiled, Error, Options, Result, plugin};
+
+/// Requests by id, with the slot of the worker handling each and its promise's resolve and reject.
+type Pending = Rc<RefCell<FxHashMap<u32, (usize, Function, Function)>>>;
+
+struct Pool {
+    /// Workers by slot, `None` once failed.
+    workers: Vec<Option<Worker>>,
+    next: usize,
+    id: u32,
+    pending: Pending,
+}
+
+thread_local! {
+    static POOL: RefCell<Option<Pool>> = const { RefCell::new(None) };
+}
+
+fn reply(pending: &Pending, data: JsValue) {
+    let id = Reflect::get(&data, &"id".into()).ok().and_then(|v| v.as_f64()).unwrap_or_default() as u32;
+    let request = pending.borrow_mut().remove(&id);
+    if let Some((_, resolve, reject)) = request {
+        match Reflect::get(&data, &"error".into()) {
+            Ok(error) if !error.is_undefined() => reject.call1(&JsValue::UNDEFINED, &error),
+            _ => resolve.call1(&JsValue::UNDEFINED, &data),
+        }.ok();
+    }
+}
+
+/// Drops the worker in `slot` of the pool `pending` belongs to and rejects what it was compiling.
+fn fail(pending: &Pending, slot: usize, error: JsValue) {
+    POOL.with(|pool| {
+        if let Some(pool) = pool.borrow_mut().as_mut().filter(|pool| Rc::ptr_eq(&pool.pending, pending)) {
+            pool.workers[slot].take().as_ref().map(Worker::terminate);
+        }
+    });
+    let rejected: Vec<_> = {
+        let mut pending = pending.borrow_mut();
+        let ids: Vec<_> = pending.iter().filter(|(_, (s, ..))| *s == slot).map(|(id, _)| *id).collect();
+        ids.iter().filter_map(|id| pending.remove(id)).collect()
+    };
+    for (_, _, reject) in rejected {
+        reject.call1(&JsValue::UNDEFINED, &error).ok();
+    }
+}
+
+#[wasm_bindgen(js_name = startPool)]
+pub fn start_pool(worker_url: &str, size: usize) -> std::result::Result<(), JsValue> {
+    let pending: Pending = Default::default();
+    let mut opts = WorkerOptions::new();
+    opts.type_(WorkerType::Module);
+
+    let workers = (0..size.max(1)).map(|slot| {
+        let worker = Worker::new_with_options(worker_url, &opts)?;
+        let replies = pending.clone();
+        let onmessage = Closure::wrap(Box::new(move |e: MessageEvent| reply(&replies, e.data())) as Box<dyn FnMut(MessageEvent)>);
+        worker.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+        onmessage.forget();
+        // `error` is an uncaught exception or a failed script load, `messageerror` a reply that
+        // can't be deserialized.
+        for (event, set) in [("error", Worker::set_onerror as fn(&Worker, Option<&Function>)), ("messageerror", Worker::set_onmessageerror)] {
+            let failed = pending.clone();
+            let url = worker_url.to_string();
+            let onfailure = Closure::wrap(Box::new(move |e: JsValue| {
+                let message = Reflect::get(&e, &"message".into()).ok().and_then(|m| m.as_string()).unwrap_or_else(|| event.to_string());
+                fail(&failed, slot, js_sys::Error::new(&format!("compiler worker {} failed: {}", url, message)).into());
+            }) as Box<dyn FnMut(JsValue)>);
+            set(&worker, Some(onfailure.as_ref().unchecked_ref()));
+            onfailure.forget();
+        }
+        Ok(Some(worker))
+    }).collect::<std::result::Result<Vec<_>, JsValue>>()?;
+
+    stop_pool();
+    POOL.with(|pool| *pool.borrow_mut() = Some(Pool { workers, next: 0, id: 0, pending }));
+    Ok(())
+}
+
+#[wasm_bindgen(js_name = stopPool)]
+pub fn stop_pool() {
+    if let Some(pool) = POOL.with(|pool| pool.borrow_mut().take()) {
+        pool.workers.iter().flatten().for_each(Worker::terminate);
+    }
+}
+
+fn request(filename: &str, input: &str, options: &Options) -> Result<Option<Promise>> {
+    POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        let pool = match pool.as_mut() {
+            Some(pool) => pool,
+            None => return Ok(None),
+        };
+        let len = pool.workers.len();
+        let index = match (0..len).map(|i| (pool.next + i) % len).find(|&i| pool.workers[i].is_some()) {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+        let id = pool.id;
+        pool.id += 1;
+        pool.next = index + 1;
+
+        let pending = pool.pending.clone();
+        let promise = Promise::new(&mut |resolve, reject| { pending.borrow_mut().insert(id, (index, resolve, reject)); });
+
+        let msg = Object::new();
+        Reflect::set(&msg, &"id".into(), &id.into())?;
+        Reflect::set(&msg, &"filename".into(), &filename.into())?;
+        Reflect::set(&msg, &"input".into(), &input.into())?;
+        Reflect::set(&msg, &"options".into(), &options.to_js()?)?;
+        pool.workers[index].as_ref().ok_or(Error::InvalidWorkerReply)?.post_message(&msg)?;
+        Ok(Some(promise))
+    })
+}
+
+/// Compiles on the pool when one is running, otherwise on the current thread. Workers don't see
+/// plugins registered here, so those modules compile here too.
+pub(crate) async fn compile(filename: &str, input: &str, options: &Options) -> Result<Compiled> {
+    if !plugin::names().is_empty() {
+        return crate::compile(filename, input, options);
+    }
+    match request(filename, input, options)? {
+        None => crate::compile(filename, input, options),
+        Some(promise) => Compiled::from_js(&JsFuture::from(promise).await?).ok_or(Error::InvalidWorkerReply),
+    }
+}
@@ -0,0 +1,93 @@
+//! Module graph prefetching. Without it the loader only discovers an import when the importing
+//! module's `define` runs, so fetches happen one level at a time. Here every fetched file is
+//! scanned with `deps::analyze` and its static imports are requested right away; `graph` resolves
+//! once the whole transitive graph has arrived, and evaluation starts after that.
+
+use std::{cell::{Cell, RefCell}, rc::Rc};
+use fxhash::{FxHashMap, FxHashSet};
+use js_sys::{Array, Promise};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::{JsFuture, future_to_promise};
+
+use crate::{Result, deps::{self, DependencyKind}, loader, resolve::resolve};
+
+thread_local! {
+    /// Fetches by path, numbered so a failed one only evicts itself.
+    static SOURCES: RefCell<FxHashMap<String, (u32, Promise)>> = Default::default();
+    static FETCHES: Cell<u32> = const { Cell::new(0) };
+}
+
+fn source(path: &str) -> Promise {
+    SOURCES.with(|sources| sources.borrow_mut().entry(path.to_string()).or_insert_with(|| {
+        let id = FETCHES.with(|fetches| fetches.replace(fetches.get() + 1));
+        let (loader, path) = (loader::current(), path.to_string());
+        // A failure isn't cached, so the next import of the path tries again.
+        (id, future_to_promise(async move {
+            let input = loader.load(&path).await.inspect_err(|_| SOURCES.with(|sources| {
+                let mut sources = sources.borrow_mut();
+                if sources.get(&path).is_some_and(|(fetch, _)| *fetch == id) {
+                    sources.remove(&path);
+                }
+            }))?;
+            Ok(JsValue::from_str(&input))
+        }))
+    }).1.clone())
+}
+
+/// The source of `path`, loaded at most once until `invalidate`d.
+pub(crate) async fn fetch(path: &str) -> Result<String> {
+    Ok(JsFuture::from(source(path)).await?.as_string().unwrap_or_default())
+}
+
+/// Forgets the fetched source of `path`, e.g. after the dev server reports a change.
+pub(crate) fn invalidate(path: &str) {
+    SOURCES.with(|sources| sources.borrow_mut().remove(path));
+}
+
+/// One `graph` call: the paths it has visited and the scans it hasn't awaited yet. Concurrent calls
+/// share fetched sources but each waits for its own graph.
+#[derive(Default)]
+struct Walk {
+    seen: RefCell<FxHashSet<String>>,
+    pending: RefCell<Vec<Promise>>,
+}
+
+fn visit(walk: &Rc<Walk>, path: String) {
+    if !walk.seen.borrow_mut().insert(path.clone()) {
+        return;
+    }
+    let scan = walk.clone();
+    let promise = future_to_promise(async move {
+        let input = JsFuture::from(source(&path)).await?.as_string().unwrap_or_default();
+        // Parse errors are reported when the module is compiled.
+        if let Ok(info) = deps::analyze(&path, &input) {
+            info.dependencies.iter()
+                .filter(|dep| dep.kind != DependencyKind::DynamicImport && !dep.type_only)
+                .for_each(|dep| visit(&scan, resolve(&path, &dep.specifier)));
+        }
+        Ok(JsValue::UNDEFINED)
+    });
+    walk.pending.borrow_mut().push(promise);
+}
+
+/// Fetches `entry` and everything it statically imports, in parallel. A module queues its imports
+/// before its own promise settles, so the graph is complete once a round finds nothing new.
+pub(crate) async fn graph(entry: &str) -> Result<()> {
+    let walk = Rc::new(Walk::default());
+    visit(&walk, entry.to_string());
+    loop {
+        let round: Array = walk.pending.borrow_mut().drain(..).collect();
+        if round.length() == 0 {
+            return Ok(());
+        }
+        JsFuture::from(Promise::all(&round)).await?;
+    }
+}
+
+/// Fetches the module graph rooted at `path`, then compiles and evaluates `path`. Its imports are
+/// evaluated through the runtime loader from the prefetched sources.
+pub(crate) async fn import(path: &str) -> Result<u64> {
+    graph(path).await?;
+    let input = fetch(path).await?;
+    crate::load(path, &input).await
+}
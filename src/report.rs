@@ -0,0 +1,222 @@
+//! Runtime error reporting. Every evaluated module's source map is kept by path, which is also the
+//! module's `sourceURL`, so stack frames pointing into generated code can be mapped back to the
+//! TypeScript they came from. Uncaught errors and unhandled rejections are reported with their
+//! original location on the console and to the callback set with `onError`.
+
+use std::{cell::RefCell, sync::{Arc, Mutex}};
+use fxhash::FxHashMap;
+use js_sys::{Function, Reflect};
+use serde::{Deserialize, Serialize};
+use swc_common::{SourceMap, Span, errors::{ColorConfig, DiagnosticBuilder, Emitter, EmitterWriter, Handler, HandlerFlags}, sync::Lrc};
+use wasm_bindgen::{JsCast, prelude::*};
+use web_sys::EventTarget;
+
+use crate::{api::ErrorCallback, overlay};
+
+struct Entry {
+    map: String,
+    /// Lines the evaluator puts in front of the code, e.g. the `function anonymous(` header of
+    /// `new Function`.
+    offset: u32,
+    splice: Option<Splice>,
+    source: Option<String>,
+}
+
+/// Text the runtime inserted into generated code after its source map was built, e.g. the module
+/// key in `define(7, [...`. Positions are 0-based, columns in UTF-16 units like stack traces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Splice {
+    pub line: u32,
+    pub column: u32,
+    pub len: u32,
+}
+
+impl Splice {
+    /// The splice of `inserted` at byte offset `at` of `code`.
+    pub fn at(code: &str, at: usize, inserted: &str) -> Splice {
+        let start = code[..at].rfind('\n').map_or(0, |i| i + 1);
+        Splice {
+            line: code[..at].matches('\n').count() as u32,
+            column: code[start..at].encode_utf16().count() as u32,
+            len: inserted.encode_utf16().count() as u32,
+        }
+    }
+
+    /// Maps a column of the evaluated code back to the code the source map describes.
+    fn column(&self, line: u32, column: u32) -> u32 {
+        match line == self.line && column >= self.column {
+            true => column.saturating_sub(self.len).max(self.column),
+            false => column,
+        }
+    }
+}
+
+thread_local! {
+    static MODULES: RefCell<FxHashMap<String, Entry>> = Default::default();
+    static CALLBACK: RefCell<Option<Function>> = RefCell::new(None);
+}
+
+/// A position in a TypeScript module, 1-based like editors and stack traces.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+    pub snippet: Option<String>,
+}
+
+/// A compile error, or a runtime error at its innermost mapped frame.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub message: String,
+    pub location: Option<Location>,
+}
+
+/// Diagnostics a `Handler` emitted, kept with their spans until there is a `SourceMap` to resolve
+/// them against.
+#[derive(Clone, Default)]
+pub(crate) struct Collected(Arc<Mutex<Vec<Emitted>>>);
+
+/// A diagnostic's message and primary span.
+type Emitted = (String, Option<Span>);
+
+impl Collected {
+    pub(crate) fn take(&self, cm: &SourceMap) -> Vec<Diagnostic> {
+        let emitted = self.0.lock().map(|mut emitted| emitted.drain(..).collect()).unwrap_or_else(|_| vec![]);
+        emitted.into_iter().map(|(message, span): (String, Option<Span>)| {
+            let location = span.map(|span| {
+                let loc = cm.lookup_char_pos(span.lo);
+                let snippet = loc.file.get_line(loc.line - 1).map(|line| line.to_string());
+                Location { file: loc.file.name.to_string(), line: loc.line as u32, column: loc.col.0 as u32 + 1, snippet }
+            });
+            Diagnostic { message, location }
+        }).collect()
+    }
+}
+
+struct Capture {
+    inner: EmitterWriter,
+    collected: Collected,
+}
+
+impl Emitter for Capture {
+    fn emit(&mut self, db: &DiagnosticBuilder<'_>) {
+        if let Ok(mut emitted) = self.collected.0.lock() {
+            emitted.push((db.message(), db.span.primary_span()));
+        }
+        self.inner.emit(db);
+    }
+}
+
+/// A handler that prints like `Handler::with_tty_emitter` and also records what it emits.
+pub(crate) fn handler(cm: &Lrc<SourceMap>) -> (Handler, Collected) {
+    let collected = Collected::default();
+    let inner = EmitterWriter::stderr(ColorConfig::Auto, Some(cm.clone()), false, false);
+    let flags = HandlerFlags { can_emit_warnings: true, ..Default::default() };
+    (Handler::with_emitter_and_flags(Box::new(Capture { inner, collected: collected.clone() }), flags), collected)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub message: String,
+    /// The module the error was attributed to, when no frame could be mapped.
+    pub module: Option<String>,
+    pub frames: Vec<Location>,
+}
+
+/// Keeps the source map of the module evaluated as `path`, `offset` lines into the evaluated code.
+pub fn register(path: &str, map: &str, offset: u32, splice: Option<Splice>) {
+    MODULES.with(|modules| {
+        let mut modules = modules.borrow_mut();
+        let source = modules.remove(path).and_then(|entry| entry.source);
+        modules.insert(path.to_string(), Entry { map: map.to_string(), offset, splice, source });
+    });
+}
+
+/// Keeps the original source of `path` for snippets.
+pub(crate) fn source(path: &str, input: &str) {
+    MODULES.with(|modules| {
+        modules.borrow_mut().entry(path.to_string())
+            .or_insert_with(|| Entry { map: String::new(), offset: 0, splice: None, source: None })
+            .source = Some(input.to_string());
+    });
+}
+
+/// Splits a stack frame into URL, line and column. Handles V8's `at f (url:1:2)` and `at url:1:2`
+/// as well as Firefox and Safari's `f@url:1:2`.
+fn frame(line: &str) -> Option<(&str, u32, u32)> {
+    let line = line.trim().trim_end_matches(')');
+    let mut parts = line.rsplitn(3, ':');
+    let column = parts.next()?.parse().ok()?;
+    let row = parts.next()?.parse().ok()?;
+    let rest = parts.next()?;
+    let start = [rest.rfind('('), rest.rfind('@'), rest.rfind("at ").map(|i| i + 2)].iter().flatten().max().map_or(0, |i| i + 1);
+    Some((rest[start..].trim(), row, column))
+}
+
+fn lookup(url: &str, line: u32, column: u32) -> Option<Location> {
+    MODULES.with(|modules| {
+        let modules = modules.borrow();
+        // `sourceURL`s may come back resolved against the page.
+        let (path, entry) = modules.iter().filter(|(path, _)| url.ends_with(path.as_str())).max_by_key(|(path, _)| path.len())?;
+        let map = sourcemap::SourceMap::from_slice(entry.map.as_bytes()).ok()?;
+        let line = line.checked_sub(1 + entry.offset)?;
+        let column = entry.splice.map_or(column.saturating_sub(1), |splice| splice.column(line, column.saturating_sub(1)));
+        let token = map.lookup_token(line, column)?;
+        let snippet = entry.source.as_ref().and_then(|s| s.lines().nth(token.get_src_line() as usize)).map(|s| s.to_string());
+        Some(Location { file: path.clone(), line: token.get_src_line() + 1, column: token.get_src_col() + 1, snippet })
+    })
+}
+
+/// Maps every frame of a stack trace that points into a twasm module.
+pub fn map_stack(stack: &str) -> Vec<Location> {
+    stack.lines().filter_map(frame).filter_map(|(url, line, column)| lookup(url, line, column)).collect()
+}
+
+fn report(error: &JsValue) {
+    let get = |name: &str| Reflect::get(error, &name.into()).ok().filter(|v| !v.is_undefined());
+    let message = get("message").and_then(|v| v.as_string()).or_else(|| error.as_string()).unwrap_or_else(|| format!("{:?}", error));
+    let frames = get("stack").and_then(|v| v.as_string()).map(|s| map_stack(&s)).unwrap_or_default();
+    let report = Report { message, module: get("module").and_then(|v| v.as_string()), frames };
+    if report.frames.is_empty() && report.module.is_none() {
+        return;
+    }
+
+    match report.frames.first() {
+        Some(at) => crate::error(&format!("{}\n    at {}:{}:{}\n    {}", report.message, at.file, at.line, at.column, at.snippet.as_deref().unwrap_or("").trim())),
+        None => crate::error(&format!("{}\n    in {}", report.message, report.module.as_deref().unwrap_or_default())),
+    }
+    let message = match (&report.module, report.frames.is_empty()) {
+        (Some(module), true) => format!("{} (in {})", report.message, module),
+        _ => report.message.clone(),
+    };
+    overlay::show("Runtime error", &[Diagnostic { message, location: report.frames.first().cloned() }]).ok();
+    if let Some(callback) = CALLBACK.with(|c| c.borrow().clone()) {
+        if let Some(value) = serde_json::to_string(&report).ok().and_then(|json| js_sys::JSON::parse(&json).ok()) {
+            callback.call1(&JsValue::UNDEFINED, &value).ok();
+        }
+    }
+}
+
+/// Listens for uncaught errors and unhandled rejections in the current global scope.
+pub(crate) fn install() -> Result<(), JsValue> {
+    let target: EventTarget = js_sys::global().dyn_into()?;
+    let onerror = Closure::wrap(Box::new(|e: JsValue| {
+        if let Ok(error) = Reflect::get(&e, &"error".into()) { report(&error) }
+    }) as Box<dyn FnMut(JsValue)>);
+    let onrejection = Closure::wrap(Box::new(|e: JsValue| {
+        if let Ok(reason) = Reflect::get(&e, &"reason".into()) { report(&reason) }
+    }) as Box<dyn FnMut(JsValue)>);
+    target.add_event_listener_with_callback("error", onerror.as_ref().unchecked_ref())?;
+    target.add_event_listener_with_callback("unhandledrejection", onrejection.as_ref().unchecked_ref())?;
+    onerror.forget();
+    onrejection.forget();
+    Ok(())
+}
+
+/// Sets the callback that receives `{ message, module, frames: [{ file, line, column, snippet }] }`
+/// for runtime errors raised by twasm modules.
+#[wasm_bindgen(js_name = onError)]
+pub fn on_error(callback: Option<ErrorCallback>) {
+    CALLBACK.with(|c| *c.borrow_mut() = callback.map(JsCast::unchecked_into));
+}
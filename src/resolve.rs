@@ -0,0 +1,34 @@
+//! Import specifier resolution shared by the bundler. Mirrors `resolve` in `runtime.js` so a
+//! project resolves to the same files whether it is transpiled live or prebundled.
+
+/// The length of the `scheme://host` a URL starts with.
+fn origin(path: &str) -> Option<usize> {
+    let host = path.find("://")? + 3;
+    Some(path[host..].find('/').map_or(path.len(), |i| host + i))
+}
+
+/// Resolves `spec` as imported from the module at `from`. Relative specifiers are joined onto the
+/// importer's directory, everything else is taken from the root, and a missing `.ts`/`.tsx`
+/// extension defaults to `.ts`. A URL is taken as is, and is the root of what it imports.
+pub fn resolve(from: &str, spec: &str) -> String {
+    if origin(spec).is_some() {
+        return spec.to_string();
+    }
+    let (root, from) = from.split_at(origin(from).unwrap_or(0));
+    let relative = spec.starts_with('.');
+    let base = if relative { &from[..from.rfind('/').map_or(0, |i| i + 1)] } else { "" };
+
+    let mut parts = vec![];
+    let joined = format!("{}{}", base, spec);
+    for part in joined.split('/') {
+        match part {
+            ".." => { parts.pop(); }
+            "." | "" => {}
+            part => parts.push(part),
+        }
+    }
+
+    let absolute = !root.is_empty() || spec.starts_with('/') || (relative && from.starts_with('/'));
+    let path = format!("{}{}{}", root, if absolute { "/" } else { "" }, parts.join("/"));
+    if path.ends_with(".ts") || path.ends_with(".tsx") { path } else { format!("{}.ts", path) }
+}
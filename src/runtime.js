@@ -0,0 +1,173 @@
+// Module runtime for the AMD and `System.register` output of `compile`. `install` puts `define`
+// on the global object so injected scripts can register themselves; modules are keyed by the
+// keyid returned from `evaluate` and cached by path so each file is only loaded once.
+
+const modules = new Map();
+const paths = new Map();
+const instances = new Map();
+const hotData = new Map();
+const scripts = new Map();
+const policies = new Map();
+let importer;
+
+// Tags an error with the module it came from, for errors surfacing away from `evaluate`.
+const attribute = (error, path) => {
+    if (error && typeof error === 'object' && !error.module) error.module = path;
+    return error;
+};
+
+const record = key => {
+    if (!modules.has(key)) {
+        const m = { key, path: undefined, exports: {}, accept: [], dispose: [], importers: [] };
+        m.ready = new Promise((resolve, reject) => { m.resolve = resolve; m.reject = reject; });
+        m.registered = new Promise(resolve => { m.register = resolve; });
+        m.hot = {
+            get data() { return hotData.get(m.path); },
+            accept(cb) { m.accept.push(cb || (() => {})); },
+            dispose(cb) { m.dispose.push(cb); },
+        };
+        modules.set(key, m);
+    }
+    return modules.get(key);
+};
+
+// The `scheme://host` a URL starts with, which resolution never climbs out of.
+const origin = path => (/^[a-z][a-z0-9+.-]*:\/\/[^/]*/i.exec(path) || [''])[0];
+
+const resolve = (from, spec) => {
+    if (origin(spec)) return spec;
+    const root = origin(from);
+    from = from.slice(root.length);
+    const base = spec.startsWith('.') ? from.slice(0, from.lastIndexOf('/') + 1) : '';
+    const parts = [];
+    for (const p of (base + spec).split('/')) {
+        if (p === '..') parts.pop();
+        else if (p !== '.' && p !== '') parts.push(p);
+    }
+    const path = root + (root || spec.startsWith('/') || from.startsWith('/') && spec.startsWith('.') ? '/' : '') + parts.join('/');
+    return /\.tsx?$/.test(path) ? path : `${path}.ts`;
+};
+
+// Resolves to the record of the module at a path once its `define` or `System.register` ran,
+// before it executes.
+const instantiate = path => {
+    if (!instances.has(path)) {
+        instances.set(path, importer(path).then(key => record(key).registered.then(() => record(key))));
+    }
+    return instances.get(path);
+};
+
+const load = path => instantiate(path).then(m => m.ready);
+
+function define(key, deps, factory) {
+    const m = record(key);
+    m.register();
+    Promise.all(deps.map(dep => dep === 'exports' ? m.exports : load(resolve(m.path, dep))))
+        .then(args => { factory(...args); m.resolve(m.exports); })
+        .catch(error => m.reject(attribute(error, m.path)));
+}
+define.amd = true;
+
+// Instantiates a `System.register` module's graph and wires every importer's setters before
+// anything executes, so modules in a cycle see each other's bindings. AMD dependencies are
+// waited on until they have run.
+const link = (m, seen = new Set()) => {
+    if (seen.has(m)) return Promise.resolve();
+    seen.add(m);
+    return Promise.all(m.deps.map(instantiate)).then(deps => {
+        if (!m.linked) {
+            m.linked = deps;
+            deps.forEach((dep, i) => { dep.importers.push(m.setters[i]); m.setters[i](dep.exports); });
+        }
+        return Promise.all(deps.map(dep => dep.system ? link(dep, seen) : dep.ready));
+    });
+};
+
+// Schedules a linked graph in dependency order. Each module waits for the dependencies scheduled
+// before it; one still being visited closes a cycle, which is broken there.
+const schedule = m => {
+    if (!m.visited) {
+        m.visited = true;
+        m.linked.filter(dep => dep.system).forEach(schedule);
+        m.done = Promise.all(m.linked.filter(dep => dep.done).map(dep => dep.done)).then(() => m.execute());
+    }
+    return m.done;
+};
+
+// Importers' setters rerun on every `_export`, so `System.register` bindings stay live where AMD
+// only hands over the exports object once.
+const System = {
+    register(key, deps, declare) {
+        const m = record(key);
+        const _export = (name, value) => {
+            if (typeof name === 'object') Object.assign(m.exports, name);
+            else m.exports[name] = value;
+            m.importers.forEach(setter => setter(m.exports));
+            return value;
+        };
+        const context = { id: m.path, meta: globalThis.__twasm_meta(m.path), import: spec => load(resolve(m.path, spec)) };
+        const { setters, execute } = declare(_export, context);
+        Object.assign(m, { system: true, deps: deps.map(dep => resolve(m.path, dep)), setters, execute });
+        m.register();
+        link(m)
+            .then(() => schedule(m))
+            .then(() => m.resolve(m.exports))
+            .catch(error => m.reject(attribute(error, m.path)));
+    },
+};
+
+// `load` compiles and evaluates the module at a path, resolving to its key.
+export function install(load) {
+    importer = load;
+    globalThis.define = define;
+    if (!globalThis.System) globalThis.System = System;
+    globalThis.addEventListener('error', e => scripts.has(e.filename) && attribute(e.error, scripts.get(e.filename)));
+    globalThis.__twasm_meta = path => ({ url: path, hot: record(paths.get(path)).hot });
+}
+
+export function register(key, path) {
+    const m = record(key);
+    m.path = path;
+    paths.set(path, key);
+    if (!instances.has(path)) instances.set(path, m.registered.then(() => m));
+}
+
+// Remembers which module a blob-URL script evaluates, since its errors report the URL.
+export function script(url, path) {
+    scripts.set(url, path);
+}
+
+// Passes code (`script`) or a script URL (`url`) through the named Trusted Types policy, or
+// returns it as is without a policy or Trusted Types support.
+export function trusted(name, kind, value) {
+    if (!name || !globalThis.trustedTypes) return value;
+    if (!policies.has(name)) {
+        policies.set(name, trustedTypes.createPolicy(name, { createScript: s => s, createScriptURL: s => s }));
+    }
+    return kind === 'url' ? policies.get(name).createScriptURL(value) : policies.get(name).createScript(value);
+}
+
+export function exports(key) {
+    return record(key).ready;
+}
+
+export function lookup(path) {
+    return paths.get(path);
+}
+
+// Runs the dispose handlers of a module that accepts hot updates. Returns false when
+// nothing accepts the update and the caller has to fall back to a full reload.
+export function dispose(key) {
+    const m = modules.get(key);
+    if (!m || !m.accept.length) return false;
+    const data = {};
+    m.dispose.forEach(cb => cb(data));
+    hotData.set(m.path, data);
+    return true;
+}
+
+export function accept(old, key) {
+    const m = record(key);
+    instances.set(m.path, m.registered.then(() => m));
+    m.ready.then(exports => modules.get(old).accept.forEach(cb => cb(exports)));
+}
@@ -0,0 +1,98 @@
+//! Auto-loading of `<script type="text/typescript">`. Every such tag, external (`src`) or inline,
+//! is compiled and run in document order, each one waiting for the previous to finish like classic
+//! scripts do; a `MutationObserver` queues tags added later. Loading starts at the page's first
+//! `configure` or at `DOMContentLoaded`, whichever comes first, so the page can set options and
+//! start the worker pool after initializing twasm. Inline scripts are registered as
+//! `inline-<n>.ts`, so their relative imports resolve against the page.
+
+use std::{cell::{Cell, RefCell}, collections::VecDeque};
+use wasm_bindgen::{JsCast, prelude::*};
+use wasm_bindgen_futures::{JsFuture, spawn_local};
+use web_sys::{Document, HtmlScriptElement, MutationObserver, MutationObserverInit};
+
+use crate::{Error, Result, prefetch, runtime};
+
+const SELECTOR: &str = "script[type=\"text/typescript\"]:not([data-twasm])";
+
+thread_local! {
+    static QUEUE: RefCell<VecDeque<HtmlScriptElement>> = Default::default();
+    static RUNNING: Cell<bool> = const { Cell::new(false) };
+    static INLINE: Cell<u32> = const { Cell::new(0) };
+    /// Set by `install` until loading begins.
+    static DEFERRED: Cell<bool> = const { Cell::new(false) };
+}
+
+async fn run(script: &HtmlScriptElement) -> Result<()> {
+    let key = match script.get_attribute("src") {
+        Some(src) => prefetch::import(&src).await?,
+        None => {
+            let n = INLINE.with(|n| n.replace(n.get() + 1));
+            crate::load(&format!("inline-{}.ts", n), &script.text()?).await?
+        }
+    };
+    JsFuture::from(runtime::exports(key as f64)).await?;
+    Ok(())
+}
+
+async fn drain() {
+    while let Some(script) = QUEUE.with(|queue| queue.borrow_mut().pop_front()) {
+        if let Err(e) = run(&script).await {
+            crate::error(&format!("{:?}", e));
+        }
+    }
+    RUNNING.with(|running| running.set(false));
+}
+
+/// Queues the tags not seen yet, marking them with `data-twasm`.
+fn scan(document: &Document) {
+    let found = match document.query_selector_all(SELECTOR) {
+        Ok(found) => found,
+        Err(_) => return,
+    };
+    QUEUE.with(|queue| {
+        let mut queue = queue.borrow_mut();
+        for script in (0..found.length()).filter_map(|i| found.item(i)?.dyn_into::<HtmlScriptElement>().ok()) {
+            script.set_attribute("data-twasm", "").ok();
+            queue.push_back(script);
+        }
+    });
+    if !RUNNING.with(|running| running.replace(true)) {
+        spawn_local(drain());
+    }
+}
+
+fn observe() -> Result<()> {
+    let document = web_sys::window().ok_or(Error::InvalidWindow)?.document().ok_or(Error::InvalidDocument)?;
+    let observed = document.clone();
+    let onmutation = Closure::wrap(Box::new(move |_: JsValue, _: JsValue| scan(&observed)) as Box<dyn FnMut(JsValue, JsValue)>);
+    let observer = MutationObserver::new(onmutation.as_ref().unchecked_ref())?;
+    let mut init = MutationObserverInit::new();
+    init.child_list(true).subtree(true);
+    observer.observe_with_options(&document, &init)?;
+    onmutation.forget();
+    scan(&document);
+    Ok(())
+}
+
+/// Starts loading if `install` deferred it and it hasn't started yet.
+pub(crate) fn begin() {
+    if DEFERRED.with(|deferred| deferred.replace(false)) {
+        if let Err(e) = observe() {
+            crate::error(&format!("{:?}", e));
+        }
+    }
+}
+
+/// Defers loading to `begin`, called by `configure` or once the document is parsed. When it
+/// already is, a timeout still lets the code after twasm's initialization run first.
+pub(crate) fn install() -> Result<()> {
+    let window = web_sys::window().ok_or(Error::InvalidWindow)?;
+    let document = window.document().ok_or(Error::InvalidDocument)?;
+    DEFERRED.with(|deferred| deferred.set(true));
+    let begin = Closure::once_into_js(begin);
+    match document.ready_state().as_str() {
+        "loading" => document.add_event_listener_with_callback("DOMContentLoaded", begin.unchecked_ref())?,
+        _ => { window.set_timeout_with_callback(begin.unchecked_ref())?; }
+    }
+    Ok(())
+}
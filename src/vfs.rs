@@ -0,0 +1,103 @@
+//! In-memory module sources. Files written here are served by the default source loader ahead
+//! of the network, so playgrounds and tests can import between modules that only exist as strings.
+//! Paths are matched as the resolver produces them, e.g. `/src/a.ts` importing `./b` reads
+//! `/src/b.ts`.
+//!
+//! Writes and deletions drop the prefetched source and the Cache Storage entry compiled from the
+//! previous contents, then notify the callbacks passed to `vfs.watch`. Modules already evaluated
+//! stay as they are; re-running them is up to the watcher.
+
+use std::{cell::RefCell, collections::BTreeMap};
+use js_sys::{Array, Function};
+use wasm_bindgen::{JsCast, prelude::*};
+use wasm_bindgen_futures::spawn_local;
+
+use crate::{api::VfsCallback, cache, options, prefetch};
+
+thread_local! {
+    static FILES: RefCell<BTreeMap<String, String>> = Default::default();
+    static WATCHERS: RefCell<Vec<Function>> = Default::default();
+}
+
+fn changed(path: &str, change: &str) {
+    prefetch::invalidate(path);
+    let watchers = WATCHERS.with(|watchers| watchers.borrow().clone());
+    for watcher in watchers {
+        watcher.call2(&JsValue::UNDEFINED, &path.into(), &change.into()).ok();
+    }
+}
+
+/// Stores `source` at `path`, returning what it replaced.
+pub fn write(path: &str, source: &str) -> Option<String> {
+    let old = FILES.with(|files| files.borrow_mut().insert(path.to_string(), source.to_string()));
+    changed(path, "write");
+    old
+}
+
+pub fn read(path: &str) -> Option<String> {
+    FILES.with(|files| files.borrow().get(path).cloned())
+}
+
+/// Deletes `path`, returning its source.
+pub fn remove(path: &str) -> Option<String> {
+    let old = FILES.with(|files| files.borrow_mut().remove(path))?;
+    changed(path, "delete");
+    Some(old)
+}
+
+/// The files and subdirectories (with a trailing `/`) directly inside `dir`, sorted.
+pub fn list(dir: &str) -> Vec<String> {
+    let prefix = match dir.trim_end_matches('/') {
+        "" if dir.starts_with('/') => "/".to_string(),
+        "" => String::new(),
+        dir => format!("{}/", dir),
+    };
+    let mut entries: Vec<String> = FILES.with(|files| files.borrow().keys().filter_map(|path| {
+        let rest = path.strip_prefix(prefix.as_str())?;
+        Some(rest.find('/').map_or(rest, |i| &rest[..=i]).to_string())
+    }).collect());
+    // Keys are sorted, so a subdirectory's files are adjacent.
+    entries.dedup();
+    entries
+}
+
+/// Drops the cached compile of the previous contents of `path`.
+fn evict(path: &str, old: Option<String>) {
+    if let Some(old) = old {
+        let key = cache::key(path, &old, &options::current());
+        spawn_local(async move { cache::delete(&key).await.ok(); });
+    }
+}
+
+/// The JS face of the module, used as `vfs.write(path, source)`.
+#[wasm_bindgen(js_name = vfs)]
+pub struct Vfs;
+
+#[wasm_bindgen(js_class = vfs)]
+impl Vfs {
+    pub fn write(path: &str, source: &str) {
+        evict(path, write(path, source));
+    }
+
+    pub fn read(path: &str) -> Option<String> {
+        read(path)
+    }
+
+    /// Returns whether `path` existed.
+    #[wasm_bindgen(js_name = delete)]
+    pub fn delete(path: &str) -> bool {
+        let old = remove(path);
+        let existed = old.is_some();
+        evict(path, old);
+        existed
+    }
+
+    pub fn list(dir: &str) -> Array {
+        list(dir).into_iter().map(JsValue::from).collect()
+    }
+
+    /// Calls `callback(path, "write" | "delete")` after every change.
+    pub fn watch(callback: VfsCallback) {
+        WATCHERS.with(|watchers| watchers.borrow_mut().push(callback.unchecked_into()));
+    }
+}
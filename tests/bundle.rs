@@ -0,0 +1,28 @@
+//! Bundler mode over a graph on disk.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::{fs, process::Command};
+use twasm::{Options, bundle::bundle};
+
+#[test]
+fn bundles_a_two_file_graph() {
+    let dir = std::env::temp_dir().join(format!("twasm-bundle-{}", std::process::id()));
+    fs::create_dir_all(dir.join("lib")).unwrap();
+    fs::write(dir.join("main.ts"), "import { greet } from './lib/greet';\nimport { shout } from '/lib/greet';\nconsole.log(shout(greet('bundle')));\n").unwrap();
+    fs::write(dir.join("lib/greet.ts"), "export const greet = (name: string): string => `hello ${name}`;\nexport const shout = (s: string) => s.toUpperCase();\nexport const unused = 'dropped';\n").unwrap();
+
+    let bundled = bundle(&dir.join("main.ts"), &Options::default());
+    fs::remove_dir_all(&dir).ok();
+    let code = bundled.unwrap().code;
+    assert_eq!(code.matches("define(\"lib/greet.ts\"").count(), 1);
+    assert!(code.contains("define(\"main.ts\""));
+    assert!(!code.contains("dropped"));
+    assert!(code.trim_end().ends_with("require(\"main.ts\");\n})();"));
+
+    // Runs the bundle when Node is around.
+    if let Ok(output) = Command::new("node").arg("-e").arg(&code).output() {
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "HELLO BUNDLE");
+    }
+}
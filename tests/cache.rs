@@ -0,0 +1,63 @@
+//! Compile cache backends, exercised natively without a browser.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use twasm::{Compiled, Options, cache::{CompileCache, FsCache, MemoryCache, key}};
+
+fn compiled() -> Compiled {
+    Compiled { code: "define([], function() {});".to_string(), map: "{}".to_string() }
+}
+
+#[test]
+fn key_depends_on_source_and_options() {
+    let options = Options::default();
+    let minified = Options { minify: true, ..Options::default() };
+    assert_eq!(key("a.ts", "let a = 1;", &options), key("a.ts", "let a = 1;", &options));
+    assert_ne!(key("a.ts", "let a = 1;", &options), key("a.ts", "let a = 2;", &options));
+    assert_ne!(key("a.ts", "let a = 1;", &options), key("a.ts", "let a = 1;", &minified));
+}
+
+#[test]
+fn key_is_a_sha256_digest() {
+    let key = key("a.ts", "let a = 1;", &Options::default());
+    assert_eq!(key.len(), 64);
+    assert!(key.bytes().all(|b| b.is_ascii_hexdigit()));
+}
+
+#[test]
+fn key_ignores_evaluation_options() {
+    let options = Options::default();
+    let nonced = Options { nonce: Some("r4nd0m".to_string()), overlay: true, ..Options::default() };
+    assert_eq!(key("a.ts", "let a = 1;", &options), key("a.ts", "let a = 1;", &nonced));
+}
+
+#[test]
+fn memory_cache_round_trip() {
+    let cache = MemoryCache::default();
+    assert_eq!(cache.get("k"), None);
+    cache.put("k", &compiled()).unwrap();
+    assert_eq!(cache.get("k"), Some(compiled()));
+}
+
+#[test]
+fn fs_cache_round_trip() {
+    let dir = std::env::temp_dir().join(format!("twasm-cache-test-{}", std::process::id()));
+    let cache = FsCache::new(&dir);
+    assert_eq!(cache.get("k"), None);
+    cache.put("k", &compiled()).unwrap();
+    assert_eq!(FsCache::new(&dir).get("k"), Some(compiled()));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn fs_cache_replaces_entries_without_leaving_temporary_files() {
+    let dir = std::env::temp_dir().join(format!("twasm-cache-replace-test-{}", std::process::id()));
+    let cache = FsCache::new(&dir);
+    cache.put("k", &Compiled { code: "stale".to_string(), map: "stale".to_string() }).unwrap();
+    cache.put("k", &compiled()).unwrap();
+    assert_eq!(cache.get("k"), Some(compiled()));
+    let mut files = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name().into_string().unwrap()).collect::<Vec<_>>();
+    files.sort();
+    assert_eq!(files, ["k.js", "k.js.map"]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
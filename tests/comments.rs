@@ -0,0 +1,44 @@
+//! Which source comments `compile` keeps in each `CommentMode`.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use twasm::{CommentMode, Options, compile};
+
+const SOURCE: &str = "/*! MyLib v1.0 | MIT */
+// Loads the widget.
+import { make } from './make';
+/** The shared widget. */
+export const widget = /*#__PURE__*/ make();
+/* @__PURE__ */ make();
+";
+
+fn compiled(comments: CommentMode) -> String {
+    compile("widget.ts", SOURCE, &Options { comments, ..Options::default() }).unwrap().code
+}
+
+#[test]
+fn legal_keeps_license_headers_and_pure_annotations() {
+    let code = compiled(CommentMode::Legal);
+    assert!(code.starts_with("/*! MyLib v1.0 | MIT */\n"));
+    assert!(code.contains("/*#__PURE__*/"));
+    assert!(code.contains("/* @__PURE__ */"));
+    assert!(!code.contains("Loads the widget"));
+    assert!(!code.contains("The shared widget"));
+}
+
+#[test]
+fn all_keeps_every_comment() {
+    let code = compiled(CommentMode::All);
+    assert!(code.starts_with("/*! MyLib v1.0 | MIT */\n"));
+    assert!(code.contains("// Loads the widget."));
+    assert!(code.contains("/** The shared widget. */"));
+    assert!(code.contains("/*#__PURE__*/"));
+    assert!(code.contains("/* @__PURE__ */"));
+}
+
+#[test]
+fn none_drops_every_comment() {
+    let code = compiled(CommentMode::None);
+    assert!(!code.contains("/*"));
+    assert!(!code.contains("//"));
+}
@@ -0,0 +1,44 @@
+//! Compile-time constants.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use twasm::{ModuleFormat, Options, compile};
+
+#[test]
+fn replaces_constants_and_drops_dead_branches() {
+    let mut options = Options { module: ModuleFormat::Es, ..Options::default() };
+    options.define.insert("process.env.NODE_ENV".to_string(), "\"production\"".to_string());
+    options.define.insert("__DEV__".to_string(), "false".to_string());
+    options.define.insert("import.meta.env.API".to_string(), "\"/api\"".to_string());
+
+    let code = compile("app.ts", "
+        if (process.env.NODE_ENV !== 'production') { console.log('development build'); }
+        export const dev: boolean = __DEV__;
+        export const api = import.meta.env.API;
+        export const flag = settings.__DEV__;
+    ", &options).unwrap().code;
+    assert!(!code.contains("development build"));
+    assert!(!code.contains("process.env"));
+    assert!(code.contains("dev = false"));
+    assert!(code.contains("api = \"/api\""));
+    assert!(code.contains("settings.__DEV__"));
+}
+
+#[test]
+fn leaves_locals_and_assignment_targets_alone() {
+    let mut options = Options { module: ModuleFormat::Es, ..Options::default() };
+    options.define.insert("process.env.NODE_ENV".to_string(), "\"production\"".to_string());
+    options.define.insert("__DEV__".to_string(), "false".to_string());
+
+    let code = compile("app.ts", "
+        export function f(__DEV__: boolean) { return __DEV__; }
+        export function g(process: any) { return process.env.NODE_ENV; }
+        export function h() { __DEV__ = true; __DEV__++; process.env.NODE_ENV = 'test'; }
+    ", &options).unwrap().code;
+    assert!(code.contains("return __DEV__"));
+    assert!(code.contains("return process.env.NODE_ENV"));
+    assert!(code.contains("__DEV__ = true"));
+    assert!(code.contains("__DEV__++"));
+    assert!(code.contains("process.env.NODE_ENV = 'test'"));
+    assert!(!code.contains("false = true"));
+}
@@ -0,0 +1,37 @@
+//! Import/export extraction.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use twasm::deps::{DependencyKind, analyze};
+
+#[test]
+fn lists_imports_reexports_and_exports() {
+    let source = "import { a, b as c } from './a';\nimport type { T } from './t';\nexport * from './star';\nexport { d as e } from './d';\nexport const x = 1, { y } = {};\nexport default function () {}\nconst m = import('./lazy');\nimport(name);\nconsole.log(a, c);\n";
+    let info = analyze("m.ts", source).unwrap();
+
+    let deps: Vec<_> = info.dependencies.iter().map(|d| (d.specifier.as_str(), d.kind, d.type_only)).collect();
+    assert_eq!(deps, vec![
+        ("./a", DependencyKind::Import, false),
+        ("./t", DependencyKind::Import, true),
+        ("./star", DependencyKind::ReExport, false),
+        ("./d", DependencyKind::ReExport, false),
+        ("./lazy", DependencyKind::DynamicImport, false),
+    ]);
+    assert_eq!(info.dependencies[0].names, Some(vec!["a".to_string(), "b".to_string()]));
+    assert_eq!(info.dependencies[2].names, None);
+
+    let first = &info.dependencies[0].span;
+    assert_eq!(&source[first.start as usize..first.end as usize], "import { a, b as c } from './a';");
+
+    let exports: Vec<_> = info.exports.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(exports, vec!["e", "x", "y", "default"]);
+}
+
+#[test]
+fn imports_only_used_as_types_are_type_only() {
+    let source = "import { Props } from './props';\nimport { render } from './render';\nimport './side-effect';\nexport const view = (props: Props) => render(props);\n";
+    let info = analyze("view.ts", source).unwrap();
+
+    let deps: Vec<_> = info.dependencies.iter().map(|d| (d.specifier.as_str(), d.type_only)).collect();
+    assert_eq!(deps, vec![("./props", true), ("./render", false), ("./side-effect", false)]);
+}
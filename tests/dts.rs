@@ -0,0 +1,100 @@
+//! Declaration emit from annotated sources.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use twasm::dts::declarations;
+
+#[test]
+fn keeps_signatures_and_drops_bodies() {
+    let dts = declarations("a.ts", "
+        export interface Point { x: number; y: number }
+        export function norm(p: Point): number { return Math.sqrt(p.x * p.x + p.y * p.y); }
+        export const origin: Point = { x: 0, y: 0 };
+        export const answer = 42;
+        const hidden = norm(origin);
+    ").unwrap();
+    assert!(dts.contains("interface Point"));
+    assert!(dts.contains("function norm(p: Point): number;"));
+    assert!(dts.contains("origin: Point;"));
+    assert!(dts.contains("answer: 42;"));
+    assert!(!dts.contains("Math.sqrt"));
+    assert!(!dts.contains("hidden"));
+}
+
+#[test]
+fn rejects_exports_that_need_inference() {
+    assert!(declarations("a.ts", "export function f(a: number) { return a; }").is_err());
+    assert!(declarations("a.ts", "export const x = Math.random();").is_err());
+}
+
+#[test]
+fn drops_the_implementation_of_overloads() {
+    let dts = declarations("a.ts", "
+        export function parse(input: string): number;
+        export function parse(input: number): number;
+        export function parse(input: string | number): number { return Number(input); }
+        export class Box {
+            get(key: string): string;
+            get(key: number): number;
+            get(key: string | number): string | number { return key; }
+        }
+    ").unwrap();
+    assert!(dts.contains("function parse(input: string): number;"));
+    assert!(dts.contains("function parse(input: number): number;"));
+    assert!(!dts.contains("string | number): number"));
+    assert!(dts.contains("get(key: string): string;"));
+    assert!(dts.contains("get(key: number): number;"));
+    assert!(!dts.contains("string | number): string | number"));
+}
+
+#[test]
+fn keeps_local_declarations_exports_refer_to() {
+    let dts = declarations("a.ts", "
+        class Base { id: number = 0; }
+        const defaults: Options = { verbose: false };
+        interface Options { verbose: boolean }
+        function helper(): void {}
+        const unused = compute();
+        export class Widget extends Base {}
+        export const settings: typeof defaults = defaults;
+        export { helper };
+    ").unwrap();
+    assert!(dts.contains("declare class Base"));
+    assert!(!dts.contains("export declare class Base"));
+    assert!(dts.contains("declare const defaults: Options;"));
+    assert!(dts.contains("declare function helper(): void;"));
+    assert!(dts.contains("export { helper }"));
+    assert!(!dts.contains("unused"));
+}
+
+#[test]
+fn keeps_private_members_as_names() {
+    let dts = declarations("a.ts", "
+        export class Account {
+            private balance: number = 0;
+            private audit(): void {}
+            #token: string = '';
+            deposit(amount: number): void { this.balance += amount; this.audit(); }
+        }
+    ").unwrap();
+    assert!(dts.contains("private balance;"));
+    assert!(dts.contains("private audit;"));
+    assert!(dts.contains("#private;"));
+    assert!(!dts.contains("#token"));
+    assert!(dts.contains("deposit(amount: number): void;"));
+}
+
+#[test]
+fn declares_parameter_properties_as_class_properties() {
+    let dts = declarations("a.ts", "
+        export class Counter {
+            constructor(private step: number, public readonly start: number = 0, protected label?: string) {}
+        }
+    ").unwrap();
+    assert!(dts.contains("readonly start: number;"));
+    assert!(dts.contains("private step;"));
+    assert!(dts.contains("protected label?: string;"));
+    assert!(dts.contains("constructor(step: number, start?: number, label?: string);"));
+    assert!(!dts.contains("= 0"));
+    assert!(!dts.contains("public"));
+}
@@ -0,0 +1,59 @@
+//! `isolatedModules` checks, reported through `compile`.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use twasm::{Error, Options, compile};
+
+fn messages(source: &str) -> Vec<String> {
+    messages_in("m.ts", source)
+}
+
+fn messages_in(filename: &str, source: &str) -> Vec<String> {
+    match compile(filename, source, &Options::default()) {
+        Ok(_) => vec![],
+        Err(Error::Diagnostics(diagnostics)) => diagnostics.into_iter().map(|d| d.message).collect(),
+        Err(e) => panic!("{:?}", e),
+    }
+}
+
+#[test]
+fn re_exported_types_need_export_type() {
+    let errors = messages("interface Local {}\nexport { Local };");
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("`Local` is a type"));
+
+    let errors = messages("import { Props } from './props';\nexport const empty: Props = {};\nexport { Props };");
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("`Props` is a type"));
+
+    assert!(messages("import type { Props } from './props';\nexport type { Props };").is_empty());
+    assert!(messages("import { render } from './render';\nrender();\nexport { render };").is_empty());
+    assert!(messages("import { value } from './value';\nexport type T = typeof value;\nexport { value };").is_empty());
+}
+
+#[test]
+fn exported_const_enums_are_rejected() {
+    let errors = messages("export const enum Direction { Up, Down }");
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("const enum `Direction`"));
+
+    assert!(messages("const enum Local { A }\nexport const a = Local.A;").is_empty());
+    assert!(messages("export enum Direction { Up, Down }").is_empty());
+}
+
+#[test]
+fn types_merged_with_namespaces_are_values() {
+    assert!(messages("interface Shape { area(): number }\nnamespace Shape { export const unit = 1; }\nexport { Shape };").is_empty());
+    assert!(messages("type Id = string;\nfunction Id(s: string): Id { return s; }\nexport { Id };").is_empty());
+}
+
+#[test]
+fn shorthand_properties_and_jsx_elements_use_values() {
+    assert!(messages("import { render } from './render';\nexport const api = { render };\nexport { render };").is_empty());
+    assert!(messages_in("m.tsx", "import { Button } from './button';\nexport const ok = <Button />;\nexport { Button };").is_empty());
+    assert!(messages_in("m.tsx", "import { ui } from './ui';\nexport const ok = <ui.Button />;\nexport { ui };").is_empty());
+
+    let errors = messages_in("m.tsx", "import { Props } from './props';\nexport const ok = (p: Props) => <div />;\nexport { Props };");
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("`Props` is a type"));
+}
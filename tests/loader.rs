@@ -0,0 +1,32 @@
+//! Source loaders.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::{future::Future, ptr, task::{Context, Poll, RawWaker, RawWakerVTable, Waker}};
+use twasm::{Error, loader::{Chain, Fs, SourceLoader, Vfs}, vfs};
+
+/// The native loaders never wait, so one poll settles them.
+fn ready<T>(future: impl Future<Output = T>) -> T {
+    fn raw() -> RawWaker { RawWaker::new(ptr::null(), &VTABLE) }
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(|_| raw(), |_| {}, |_| {}, |_| {});
+    let waker = unsafe { Waker::from_raw(raw()) };
+    match Box::pin(future).as_mut().poll(&mut Context::from_waker(&waker)) {
+        Poll::Ready(value) => value,
+        Poll::Pending => panic!("loader is pending"),
+    }
+}
+
+#[test]
+fn chain_falls_through_missing_files() {
+    let dir = std::env::temp_dir().join(format!("twasm-loader-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src/disk.ts"), "export const disk = 1;").unwrap();
+    vfs::write("/src/memory.ts", "export const memory = 2;");
+
+    let loader = Chain(vec![Box::new(Vfs), Box::new(Fs::new(&dir))]);
+    assert_eq!(ready(loader.load("/src/disk.ts")).unwrap(), "export const disk = 1;");
+    assert_eq!(ready(loader.load("/src/memory.ts")).unwrap(), "export const memory = 2;");
+    assert!(matches!(ready(loader.load("/src/none.ts")), Err(Error::NotFound(path)) if path == "/src/none.ts"));
+
+    std::fs::remove_dir_all(dir).ok();
+}
@@ -0,0 +1,41 @@
+//! `minify` output: compact, with the AMD factory's locals mangled and its exports intact.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use twasm::{Options, compile};
+
+const SOURCE: &str = "import { helper } from './helper';
+function computeTotal(items: number[]) {
+    let runningTotal = 0;
+    for (const item of items) {
+        runningTotal += helper(item);
+    }
+    return runningTotal;
+}
+export const total = computeTotal([1, 2, 3]);
+export function describeTotal(label: string) {
+    const message = label + ': ' + total;
+    return message;
+}
+";
+
+#[test]
+fn minified_output_is_compact() {
+    let minified = compile("total.ts", SOURCE, &Options { minify: true, ..Options::default() }).unwrap().code;
+    let plain = compile("total.ts", SOURCE, &Options::default()).unwrap().code;
+    assert!(!minified.trim_end().contains('\n'));
+    assert!(!minified.contains("  "));
+    assert!(minified.len() * 2 < plain.len());
+}
+
+#[test]
+fn minify_renames_locals_but_keeps_exported_names() {
+    let code = compile("total.ts", SOURCE, &Options { minify: true, ..Options::default() }).unwrap().code;
+    for local in ["items", "runningTotal", "item", "label", "message", "_exports", "_helper"] {
+        assert!(!code.contains(local), "`{}` survived in {}", local, code);
+    }
+    assert!(code.starts_with(r#"define(["exports","./helper"],function("#));
+    assert!(code.contains(".total="));
+    assert!(code.contains(".describeTotal="));
+    assert!(code.contains(".helper("));
+}
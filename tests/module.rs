@@ -0,0 +1,59 @@
+//! Output formats beyond the runtime's AMD.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use twasm::{ModuleFormat, Options, compile, module::{registration, umd}};
+
+#[test]
+fn umd_uses_configured_globals() {
+    let mut config = umd::Config { name: Some("MyLib".to_string()), ..Default::default() };
+    config.globals.insert("react".to_string(), "React".to_string());
+    let options = Options { module: ModuleFormat::Umd, umd: config, ..Options::default() };
+
+    let code = compile("lib.ts", "import React from 'react';\nexport const x: number = React.version;", &options).unwrap().code;
+    assert!(code.contains("define.amd"));
+    assert!(code.contains("require(\"react\")"));
+    assert!(code.contains("global.React"));
+    assert!(code.contains("global.MyLib = mod.exports"));
+}
+
+#[test]
+fn umd_defaults_to_camel_cased_names() {
+    let options = Options { module: ModuleFormat::Umd, ..Options::default() };
+    let code = compile("my-widget.ts", "import debounce from 'lodash.debounce';\nimport 'side-effect';\nexport const y = debounce;", &options).unwrap().code;
+    // The runtime registers the module by splicing its key into the wrapper's `define(` call.
+    assert!(code[registration(&code, ModuleFormat::Umd).unwrap()..].starts_with("[\n"));
+    assert!(code.contains("global.lodashDebounce"));
+    assert!(code.contains("global.sideEffect"));
+    assert!(code.contains("global.myWidget = mod.exports"));
+    assert!(code.contains("typeof globalThis"));
+}
+
+#[test]
+fn system_register_keeps_bindings_live() {
+    let options = Options { module: ModuleFormat::System, ..Options::default() };
+    let code = compile("counter.ts", "
+        import { step } from './step';
+        export * from './more';
+        export let count: number = 0;
+        export function increment() { count += step; count++; }
+        export default await Promise.resolve(count);
+    ", &options).unwrap().code;
+    assert!(code.starts_with("System.register(["));
+    assert!(code.find("\"./step\"") < code.find("\"./more\""));
+    assert!(code.contains("step = _m.step"));
+    assert!(code.contains("_export(\"count\", count += step)"));
+    assert!(code.contains("_export(\"count\", count + 1)"));
+    assert!(code.contains("_export(\"increment\", increment)"));
+    assert!(code.contains("execute: async function"));
+}
+
+#[test]
+fn common_js_rewrites_import_meta() {
+    let options = Options { module: ModuleFormat::CommonJs, ..Options::default() };
+    let code = compile("cli.ts", "export const here = import.meta.url;\nexport const dir = import.meta.dirname;\nexport const meta = import.meta;", &options).unwrap().code;
+    assert!(!code.contains("import.meta"));
+    assert!(code.contains("require(\"url\").pathToFileURL(__filename).href"));
+    assert!(code.contains("dir = __dirname"));
+    assert!(code.contains("filename: __filename"));
+}
@@ -0,0 +1,58 @@
+//! Transform plugins.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use swc_ecma_ast::{Expr, ExprOrSuper, ModuleItem, Stmt};
+use swc_ecma_visit::{Fold, noop_fold_type};
+use twasm::{ModuleFormat, Options, bundle::bundle, compile, plugin};
+
+struct StripDebug;
+impl Fold for StripDebug {
+    noop_fold_type!();
+
+    fn fold_module_items(&mut self, items: Vec<ModuleItem>) -> Vec<ModuleItem> {
+        items.into_iter().filter(|item| !matches!(item, ModuleItem::Stmt(Stmt::Expr(stmt)) if is_debug(&stmt.expr))).collect()
+    }
+}
+
+fn is_debug(expr: &Expr) -> bool {
+    let callee = match expr {
+        Expr::Call(call) => &call.callee,
+        _ => return false,
+    };
+    match callee {
+        ExprOrSuper::Expr(callee) => match &**callee {
+            Expr::Member(member) => matches!(&*member.prop, Expr::Ident(prop) if &*prop.sym == "debug"),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[test]
+fn runs_registered_folds_after_strip() {
+    let options = Options { module: ModuleFormat::Es, ..Options::default() };
+    let source = "const x: number = 1;\nconsole.debug(x);\nconsole.log(x);\n";
+
+    plugin::register("strip-debug", |_| Box::new(StripDebug));
+    let code = compile("a.ts", source, &options).unwrap().code;
+    assert!(!code.contains("console.debug"));
+    assert!(code.contains("console.log"));
+
+    plugin::unregister("strip-debug");
+    assert!(compile("a.ts", source, &options).unwrap().code.contains("console.debug"));
+}
+
+#[test]
+fn runs_registered_folds_when_bundling() {
+    let dir = std::env::temp_dir().join(format!("twasm-plugin-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("main.ts"), "console.debug('bundled');\nconsole.log('kept');\n").unwrap();
+
+    plugin::register("strip-debug", |_| Box::new(StripDebug));
+    let code = bundle(&dir.join("main.ts"), &Options::default()).unwrap().code;
+    plugin::unregister("strip-debug");
+    std::fs::remove_dir_all(&dir).ok();
+    assert!(!code.contains("console.debug"));
+    assert!(code.contains("console.log"));
+}
@@ -0,0 +1,51 @@
+//! Mapping runtime stack frames back to TypeScript.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use twasm::{Options, compile, report::{self, Splice, map_stack}};
+
+/// Compiles `source` as `path` and registers it the way the runtime evaluates it, with the key
+/// spliced into `define(`. Returns the evaluated code.
+fn evaluate(path: &str, source: &str, options: &Options) -> String {
+    let compiled = compile(path, source, options).unwrap();
+    let at = compiled.code.find("define(").unwrap() + "define(".len();
+    report::register(path, &compiled.map, 0, Some(Splice::at(&compiled.code, at, "7, ")));
+    compiled.code.replacen("define(", "define(7, ", 1)
+}
+
+/// The 1-based line and column of `needle` in `code`, as a stack frame reports it.
+fn position(code: &str, needle: &str) -> (usize, usize) {
+    let at = code.find(needle).unwrap();
+    let start = code[..at].rfind('\n').map_or(0, |i| i + 1);
+    (code[..at].matches('\n').count() + 1, at - start + 1)
+}
+
+const SOURCE: &str = "export function fail(reason: string) {\n    throw new Error(reason);\n}\n";
+
+#[test]
+fn maps_chrome_firefox_and_safari_frames() {
+    let code = evaluate("/frames.ts", SOURCE, &Options::default());
+    let (line, column) = position(&code, "Error(");
+    for stack in [
+        format!("Error: x\n    at fail (http://localhost:8080/frames.ts:{}:{})\n    at http://localhost:8080/app.js:1:1", line, column),
+        format!("Error: x\n    at http://localhost:8080/frames.ts:{}:{}", line, column),
+        format!("fail@http://localhost:8080/frames.ts:{}:{}\n@http://localhost:8080/app.js:1:1", line, column),
+        format!("fail@http://localhost:8080/frames.ts:{}:{}\nglobal code@http://localhost:8080/app.js:1:1", line, column),
+    ] {
+        let frames = map_stack(&stack);
+        assert_eq!(frames.len(), 1, "{}", stack);
+        assert_eq!((frames[0].file.as_str(), frames[0].line, frames[0].column), ("/frames.ts", 2, 15), "{}", stack);
+        assert_eq!(frames[0].snippet, None);
+    }
+}
+
+#[test]
+fn shifts_columns_after_the_spliced_key() {
+    let code = evaluate("/minified.ts", SOURCE, &Options { minify: true, ..Options::default() });
+    // The call's `(`, a few columns before the tokens of its arguments.
+    let (line, column) = position(&code, "Error(");
+    assert_eq!(line, 1, "{}", code);
+    let frames = map_stack(&format!("    at fail (http://localhost:8080/minified.ts:{}:{})", line, column + "Error".len()));
+    assert_eq!(frames.len(), 1);
+    assert_eq!((frames[0].line, frames[0].column), (2, 20));
+}
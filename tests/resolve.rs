@@ -0,0 +1,23 @@
+//! Import specifier resolution.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use twasm::resolve::resolve;
+
+#[test]
+fn resolves_paths() {
+    assert_eq!(resolve("/src/app.ts", "./util"), "/src/util.ts");
+    assert_eq!(resolve("/src/app.ts", "../lib/view.tsx"), "/lib/view.tsx");
+    assert_eq!(resolve("src/app.ts", "./util"), "src/util.ts");
+    assert_eq!(resolve("/src/app.ts", "/lib/x"), "/lib/x.ts");
+    assert_eq!(resolve("/src/app.ts", "lib/x"), "lib/x.ts");
+}
+
+#[test]
+fn keeps_urls_intact() {
+    assert_eq!(resolve("/index.html", "https://cdn.example.com/lib/app.ts"), "https://cdn.example.com/lib/app.ts");
+    assert_eq!(resolve("https://cdn.example.com/lib/app.ts", "./util"), "https://cdn.example.com/lib/util.ts");
+    assert_eq!(resolve("https://cdn.example.com/lib/app.ts", "../../../x"), "https://cdn.example.com/x.ts");
+    assert_eq!(resolve("https://cdn.example.com/lib/app.ts", "/shared/y"), "https://cdn.example.com/shared/y.ts");
+    assert_eq!(resolve("https://cdn.example.com/lib/app.ts", "z"), "https://cdn.example.com/z.ts");
+}
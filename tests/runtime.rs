@@ -0,0 +1,125 @@
+//! The module runtime, driven through Node with `compile` output. Skipped without `node`.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::{fs, process::Command};
+use twasm::{ModuleFormat, Options, compile, module::registration};
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("twasm-runtime-{}-{}", std::process::id(), name.trim_start_matches('/').replace('.', "-")));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Evaluates the compiled `files` through `src/runtime.js` the way `evaluate` does, keyed by
+/// position, and prints the JSON of the first module's exports.
+fn run(files: &[(&str, &str)], options: &Options) -> Option<String> {
+    let dir = temp_dir(files[0].0);
+    fs::write(dir.join("runtime.mjs"), include_str!("../src/runtime.js")).unwrap();
+    let modules = files.iter().enumerate().map(|(key, (path, source))| {
+        let code = compile(path, source, options).unwrap().code;
+        let at = registration(&code, options.module).unwrap();
+        (path.to_string(), format!("{}{}, {}", &code[..at], key, &code[at..]))
+    }).collect::<std::collections::BTreeMap<_, _>>();
+    let driver = format!(r#"
+        import {{ install, register, exports }} from './runtime.mjs';
+        globalThis.addEventListener = () => {{}};
+        const modules = {modules};
+        const keys = Object.keys(modules);
+        const evaluate = path => {{
+            const key = keys.indexOf(path);
+            register(key, path);
+            new Function(modules[path])();
+            return Promise.resolve(key);
+        }};
+        install(evaluate);
+        evaluate({entry});
+        console.log(JSON.stringify(await exports(keys.indexOf({entry}))));
+    "#, modules = serde_json::to_string(&modules).unwrap(), entry = serde_json::Value::from(files[0].0));
+    node(&dir, &driver)
+}
+
+/// Runs `script` as an ES module next to `dir`'s files and returns what it printed.
+fn node(dir: &std::path::Path, script: &str) -> Option<String> {
+    fs::write(dir.join("driver.mjs"), script).unwrap();
+    let output = Command::new("node").arg(dir.join("driver.mjs")).output().ok();
+    fs::remove_dir_all(dir).ok();
+    let output = output?;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    Some(String::from_utf8(output.stdout).unwrap().trim().to_string())
+}
+
+#[test]
+fn system_register_resolves_cycles() {
+    let options = Options { module: ModuleFormat::System, ..Options::default() };
+    let exports = run(&[
+        ("/a.ts", "import { b } from './b';\nexport function name() { return 'a'; }\nexport const value = b();"),
+        ("/b.ts", "import { name } from './a';\nexport function b() { return name() + 'b'; }"),
+    ], &options);
+    if let Some(exports) = exports {
+        assert_eq!(exports, r#"{"value":"ab"}"#);
+    }
+}
+
+#[test]
+fn system_register_keeps_user_bindings_apart_from_its_own() {
+    let options = Options { module: ModuleFormat::System, ..Options::default() };
+    let exports = run(&[
+        ("/a.ts", "import { _m } from './b';\nconst _export = 'mine';\nconst _context = 1;\nexport const value = _export + _m + _context;"),
+        ("/b.ts", "export const _m = '!';"),
+    ], &options);
+    if let Some(exports) = exports {
+        assert_eq!(exports, r#"{"value":"mine!1"}"#);
+    }
+}
+
+#[test]
+fn modules_are_keyed_at_their_registration_call_not_in_comments() {
+    for module in [ModuleFormat::Amd, ModuleFormat::System] {
+        let options = Options { module, ..Options::default() };
+        let exports = run(&[
+            ("/a.ts", "/*! Call define( or System.register( to load this file. */\nimport { b } from './b';\nexport const value = 'define(' + b;"),
+            ("/b.ts", "// System.register(\nexport const b = 1;"),
+        ], &options);
+        if let Some(exports) = exports {
+            assert_eq!(exports, r#"{"value":"define(1"}"#);
+        }
+    }
+}
+
+#[test]
+fn es_and_common_js_output_has_no_registration_call() {
+    for module in [ModuleFormat::Es, ModuleFormat::CommonJs] {
+        let options = Options { module, ..Options::default() };
+        let code = compile("a.ts", "/*! define( */\nexport const value = 1;", &options).unwrap().code;
+        assert_eq!(registration(&code, module), None);
+    }
+}
+
+#[test]
+fn umd_registers_through_the_runtime_define() {
+    let options = Options { module: ModuleFormat::Umd, ..Options::default() };
+    let exports = run(&[
+        ("/app.ts", "import { greet } from './greet';\nexport const message = greet('umd');"),
+        ("/greet.ts", "export const greet = (name: string) => `hello ${name}`;"),
+    ], &options);
+    if let Some(exports) = exports {
+        assert_eq!(exports, r#"{"message":"hello umd"}"#);
+    }
+}
+
+#[test]
+fn umd_falls_back_to_globals_without_a_loader() {
+    let mut options = Options { module: ModuleFormat::Umd, ..Options::default() };
+    options.umd.globals.insert("./greet".to_string(), "Greeter".to_string());
+    let code = compile("app.ts", "import { greet } from './greet';\nexport const message = greet('page');", &options).unwrap().code;
+    let dir = temp_dir("globals");
+    let script = format!(r#"
+        globalThis.Greeter = {{ greet: name => `hello ${{name}}` }};
+        new Function({code})();
+        console.log(JSON.stringify(globalThis.app));
+    "#, code = serde_json::Value::from(code));
+    if let Some(exports) = node(&dir, &script) {
+        assert_eq!(exports, r#"{"message":"hello page"}"#);
+    }
+}
@@ -0,0 +1,24 @@
+//! In-memory module sources.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use twasm::vfs::{list, read, remove, write};
+
+#[test]
+fn writes_lists_and_deletes() {
+    assert_eq!(write("/src/a.ts", "export const a = 1;"), None);
+    write("/src/b.ts", "export const b = 2;");
+    write("/src/lib/c.ts", "export const c = 3;");
+    write("/src/lib/d.ts", "export const d = 4;");
+    assert_eq!(write("/src/a.ts", "export const a = 5;").as_deref(), Some("export const a = 1;"));
+
+    assert_eq!(read("/src/a.ts").as_deref(), Some("export const a = 5;"));
+    assert_eq!(list("/"), vec!["src/"]);
+    assert_eq!(list("/src"), vec!["a.ts", "b.ts", "lib/"]);
+    assert_eq!(list("/src/lib/"), vec!["c.ts", "d.ts"]);
+
+    assert!(remove("/src/b.ts").is_some());
+    assert!(remove("/src/b.ts").is_none());
+    assert_eq!(read("/src/b.ts"), None);
+    assert_eq!(list("/src"), vec!["a.ts", "lib/"]);
+}
@@ -0,0 +1,18 @@
+// Compiles modules off the main thread. Start as a module worker:
+//   new Worker('worker.js', { type: 'module' })
+// or let `startPool('worker.js', n)` spawn several. Post { id, filename, input, options };
+// the reply is { id, code, map } or { id, error }, ready to hand to
+// `evaluate(filename, { code, map })` on the page.
+import init, { compile, configure } from '../pkg/twasm.js';
+
+const ready = init();
+
+onmessage = ({ data: { id, filename, input, options } }) => ready.then(() => {
+    try {
+        if (options) configure(options);
+        postMessage({ id, ...compile(filename, input) });
+    } catch (error) {
+        // Diagnostics stay structured so the page's error overlay can show them.
+        postMessage({ id, error: error && error.diagnostics ? { message: error.message, diagnostics: error.diagnostics } : String(error) });
+    }
+});